@@ -0,0 +1,139 @@
+use crate::card::transport::Transport;
+use crate::card::{Memory, find_message};
+use anyhow::anyhow;
+
+// The key A of the MIFARE Application Directory sector.
+const MAD_KEY: [u8; 6] = [0xA0, 0xA1, 0xA2, 0xA3, 0xA4, 0xA5];
+// The public key A of the NFC Forum sectors.
+const NDEF_KEY: [u8; 6] = [0xD3, 0xF7, 0xD3, 0xF7, 0xD3, 0xF7];
+// The application identifier of NFC Forum sectors in the directory.
+const NDEF_AID: [u8; 2] = [0x03, 0xE1];
+// The key slot of the reader used for authentication.
+const KEY_NUMBER: u8 = 0x00;
+// The authentication command for key A.
+const KEY_A: u8 = 0x60;
+// Number of bytes in a block.
+const BLOCK_SIZE: u8 = 0x10;
+// Number of blocks in the sectors covered by the directory, including the sector trailer.
+const SECTOR_BLOCKS: usize = 4;
+// Number of bytes of data in a sector, without the sector trailer.
+const SECTOR_DATA: usize = (SECTOR_BLOCKS - 1) * BLOCK_SIZE as usize;
+
+/// Reads the NDEF message TLV from the NFC Forum sectors of a MIFARE Classic tag,
+/// as listed in the MIFARE Application Directory.
+/// Returns none when the tag has no NFC Forum sectors.
+pub(super) fn read_message(transport: &mut impl Transport) -> anyhow::Result<Option<Vec<u8>>> {
+    load_key(transport, MAD_KEY)?;
+    authenticate(transport, 0)?;
+
+    let directory = [read_block(transport, 1)?, read_block(transport, 2)?].concat();
+    let sectors = ndef_sectors(&directory);
+    if sectors.is_empty() {
+        return Ok(None);
+    }
+
+    load_key(transport, NDEF_KEY)?;
+
+    find_message(&mut Memory::new(|offset, _| {
+        let sector = sectors
+            .get(offset / SECTOR_DATA)
+            .ok_or_else(|| anyhow!("The NDEF message runs past the NFC Forum sectors"))?;
+        let first = sector * SECTOR_BLOCKS;
+
+        // Each sector needs its own authentication before its blocks can be read.
+        if offset % SECTOR_DATA == 0 {
+            authenticate(transport, first)?;
+        }
+
+        read_block(
+            transport,
+            first + offset % SECTOR_DATA / BLOCK_SIZE as usize,
+        )
+    }))
+}
+
+/// The NFC Forum sectors in the MIFARE Application Directory,
+/// which starts with a CRC and an info byte followed by the identifiers for sectors 1 to 15.
+fn ndef_sectors(directory: &[u8]) -> Vec<usize> {
+    directory
+        .get(2..)
+        .unwrap_or_default()
+        .chunks_exact(2)
+        .enumerate()
+        .filter(|(_, aid)| *aid == NDEF_AID)
+        .map(|(index, _)| index + 1)
+        .collect()
+}
+
+/// Loads the key into the key slot of the reader.
+fn load_key(transport: &mut impl Transport, key: [u8; 6]) -> anyhow::Result<()> {
+    let mut command = vec![0xFF, 0x82, 0x00, KEY_NUMBER, 0x06];
+    command.extend_from_slice(&key);
+
+    transport.execute(&command, "load the key").map(|_| ())
+}
+
+/// Authenticates the sector of the block with key A from the key slot.
+fn authenticate(transport: &mut impl Transport, block: usize) -> anyhow::Result<()> {
+    let block = u8::try_from(block).map_err(|_| anyhow!("The block {block} is out of range"))?;
+    let command = [
+        0xFF, 0x86, 0x00, 0x00, 0x05, 0x01, 0x00, block, KEY_A, KEY_NUMBER,
+    ];
+
+    transport.execute(&command, "authenticate").map(|_| ())
+}
+
+fn read_block(transport: &mut impl Transport, block: usize) -> anyhow::Result<Vec<u8>> {
+    let block = u8::try_from(block).map_err(|_| anyhow!("The block {block} is out of range"))?;
+    let data = transport.execute(&[0xFF, 0xB0, 0x00, block, BLOCK_SIZE], "read the block")?;
+
+    if data.len() < BLOCK_SIZE as usize {
+        return Err(anyhow!("The read operation returned too few bytes"));
+    }
+
+    Ok(data[..BLOCK_SIZE as usize].to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::SUCCESS;
+
+    #[test]
+    fn finds_ndef_sectors_in_directory() {
+        let mut directory = vec![0x14, 0x01];
+        directory.extend_from_slice(&[0x03, 0xE1, 0x03, 0xE1, 0x00, 0x00, 0x03, 0xE1]);
+        directory.resize(32, 0x00);
+
+        assert_eq!(ndef_sectors(&directory), vec![1, 2, 4]);
+        assert!(ndef_sectors(&[0x14]).is_empty());
+    }
+
+    #[test]
+    fn authenticates_each_sector_before_reading() {
+        let mut commands = Vec::new();
+        let mut transport = |command: &[u8]| -> anyhow::Result<Vec<u8>> {
+            commands.push(command.to_vec());
+
+            match command[1] {
+                // Sectors 1 and 2 hold a message TLV that spans both of them.
+                0xB0 if command[3] == 1 => {
+                    Ok([&[0x14, 0x01, 0x03, 0xE1, 0x03, 0xE1][..], &[0; 10], SUCCESS].concat())
+                }
+                0xB0 if command[3] == 4 => Ok([&[0x03, 0x40][..], &[0xAB; 14], SUCCESS].concat()),
+                0xB0 => Ok([&[0xAB; 16][..], SUCCESS].concat()),
+                _ => Ok(SUCCESS.to_vec()),
+            }
+        };
+
+        let message = read_message(&mut transport).unwrap().unwrap();
+        assert_eq!(message, vec![0xAB; 0x40]);
+
+        let authenticated: Vec<u8> = commands
+            .iter()
+            .filter(|command| command[1] == 0x86)
+            .map(|command| command[7])
+            .collect();
+        assert_eq!(authenticated, vec![0, 4, 8]);
+    }
+}
@@ -0,0 +1,227 @@
+use crate::card::{Message, Tag};
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+use std::time::{Duration, Instant};
+
+/// A card in a reader and when it was removed, if it was.
+struct Presence {
+    uid: Option<String>,
+    message: Message,
+    removed: Option<Instant>,
+}
+
+/// Tells a card taken away and presented again apart from RF flicker at the edge of the field.
+/// A removal only counts once the card has been absent for the minimum absence,
+/// and the same card coming back before then is ignored as if it never left.
+pub struct Debounce {
+    min_absence: Duration,
+    readers: HashMap<CString, Presence>,
+    // The cards in the readers when the connection to them was lost.
+    lost: HashMap<CString, Presence>,
+}
+
+impl Debounce {
+    pub fn new(min_absence: Duration) -> Self {
+        Self {
+            min_absence,
+            readers: HashMap::new(),
+            lost: HashMap::new(),
+        }
+    }
+
+    /// Records the card presented at the given time.
+    /// Returns whether it counts as a new presentation rather than flicker.
+    pub fn inserted(&mut self, tag: &Tag, now: Instant) -> bool {
+        let flicker = self.readers.get(&tag.reader).is_some_and(|presence| {
+            presence.uid == tag.uid
+                && presence.message == tag.message
+                && presence
+                    .removed
+                    .is_some_and(|removed| now.duration_since(removed) < self.min_absence)
+        });
+
+        self.readers.insert(
+            tag.reader.clone(),
+            Presence {
+                uid: tag.uid.clone(),
+                message: tag.message.clone(),
+                removed: None,
+            },
+        );
+
+        !flicker
+    }
+
+    /// Records the card removed from the reader at the given time.
+    /// Returns whether the removal counts right away, otherwise it counts once it expires.
+    pub fn removed(&mut self, reader: &CStr, now: Instant) -> bool {
+        match self.readers.get_mut(reader) {
+            Some(presence) if !self.min_absence.is_zero() => {
+                presence.removed.get_or_insert(now);
+                false
+            }
+            _ => {
+                self.readers.remove(reader);
+                true
+            }
+        }
+    }
+
+    /// The readers whose card has been absent for the minimum absence by the given time.
+    pub fn expired(&mut self, now: Instant) -> Vec<CString> {
+        let expired: Vec<CString> = self
+            .readers
+            .iter()
+            .filter(|(_, presence)| {
+                presence
+                    .removed
+                    .is_some_and(|removed| now.duration_since(removed) >= self.min_absence)
+            })
+            .map(|(reader, _)| reader.clone())
+            .collect();
+
+        for reader in &expired {
+            self.readers.remove(reader);
+        }
+
+        expired
+    }
+
+    /// Forgets the cards in the readers once the connection to them was lost.
+    /// Returns the readers whose card has not counted as removed yet.
+    pub fn disconnect(&mut self) -> Vec<CString> {
+        self.lost = std::mem::take(&mut self.readers);
+        self.lost.keys().cloned().collect()
+    }
+
+    /// Whether the card was in the reader when the connection was lost, so it never left.
+    pub fn returned(&mut self, tag: &Tag) -> bool {
+        self.lost.remove(&tag.reader).is_some_and(|presence| {
+            presence.uid == tag.uid && presence.message == tag.message
+        })
+    }
+
+    /// Forgets the cards lost with the connection that were not presented again right away.
+    pub fn settle(&mut self) {
+        self.lost.clear();
+    }
+
+    /// When the next pending removal expires.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.readers
+            .values()
+            .filter_map(|presence| presence.removed)
+            .min()
+            .map(|removed| removed + self.min_absence)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIN_ABSENCE: Duration = Duration::from_millis(800);
+
+    fn tag(reader: &CStr, uid: &str) -> Tag {
+        Tag {
+            reader: reader.to_owned(),
+            uid: Some(uid.to_string()),
+            message: Message::default(),
+        }
+    }
+
+    #[test]
+    fn ignores_flicker() {
+        let start = Instant::now();
+        let mut debounce = Debounce::new(MIN_ABSENCE);
+
+        assert!(debounce.inserted(&tag(c"PICC 0", "04A1"), start));
+        assert!(!debounce.removed(c"PICC 0", start + Duration::from_millis(100)));
+        assert!(!debounce.inserted(&tag(c"PICC 0", "04A1"), start + Duration::from_millis(300)));
+
+        assert_eq!(debounce.deadline(), None);
+        assert!(debounce.expired(start + Duration::from_secs(5)).is_empty());
+    }
+
+    #[test]
+    fn counts_removal_after_min_absence() {
+        let start = Instant::now();
+        let mut debounce = Debounce::new(MIN_ABSENCE);
+
+        debounce.inserted(&tag(c"PICC 0", "04A1"), start);
+        debounce.removed(c"PICC 0", start);
+
+        assert_eq!(debounce.deadline(), Some(start + MIN_ABSENCE));
+        assert!(
+            debounce
+                .expired(start + Duration::from_millis(799))
+                .is_empty()
+        );
+        assert_eq!(
+            debounce.expired(start + MIN_ABSENCE),
+            vec![c"PICC 0".to_owned()]
+        );
+
+        // Presenting the card again after the removal counted is a re-tap.
+        assert!(debounce.inserted(&tag(c"PICC 0", "04A1"), start + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn counts_other_card_right_away() {
+        let start = Instant::now();
+        let mut debounce = Debounce::new(MIN_ABSENCE);
+
+        debounce.inserted(&tag(c"PICC 0", "04A1"), start);
+        debounce.removed(c"PICC 0", start);
+
+        assert!(debounce.inserted(&tag(c"PICC 0", "04B2"), start + Duration::from_millis(200)));
+        assert_eq!(debounce.deadline(), None);
+    }
+
+    #[test]
+    fn keeps_readers_apart() {
+        let start = Instant::now();
+        let mut debounce = Debounce::new(MIN_ABSENCE);
+
+        debounce.inserted(&tag(c"PICC 0", "04A1"), start);
+        debounce.removed(c"PICC 0", start);
+
+        assert!(debounce.inserted(&tag(c"PICC 1", "04A1"), start));
+        assert_eq!(debounce.expired(start + MIN_ABSENCE).len(), 1);
+    }
+
+    #[test]
+    fn remembers_cards_across_reconnects() {
+        let start = Instant::now();
+        let mut debounce = Debounce::new(MIN_ABSENCE);
+
+        debounce.inserted(&tag(c"PICC 0", "04A1"), start);
+        debounce.inserted(&tag(c"PICC 1", "04B2"), start);
+        debounce.removed(c"PICC 1", start);
+
+        let mut lost = debounce.disconnect();
+        lost.sort();
+        assert_eq!(lost, vec![c"PICC 0".to_owned(), c"PICC 1".to_owned()]);
+        assert_eq!(debounce.deadline(), None);
+
+        assert!(debounce.returned(&tag(c"PICC 0", "04A1")));
+        assert!(!debounce.returned(&tag(c"PICC 1", "04C3")));
+
+        debounce.disconnect();
+        debounce.settle();
+        assert!(!debounce.returned(&tag(c"PICC 0", "04A1")));
+    }
+
+    #[test]
+    fn counts_removals_right_away_without_min_absence() {
+        let start = Instant::now();
+        let mut debounce = Debounce::new(Duration::ZERO);
+
+        debounce.inserted(&tag(c"PICC 0", "04A1"), start);
+        assert!(debounce.removed(c"PICC 0", start));
+        assert!(debounce.inserted(&tag(c"PICC 0", "04A1"), start));
+
+        // Removals from readers without a card have nothing to wait for.
+        assert!(Debounce::new(MIN_ABSENCE).removed(c"PICC 1", start));
+    }
+}
@@ -0,0 +1,255 @@
+use pcsc::{Context, ctl_code};
+use std::ffi::{CStr, CString};
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+/// The outcome of presenting a card, signalled to the person holding it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    Success,
+    Failure,
+}
+
+/// A step in a feedback sequence for a reader.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+    /// Send the escape command to the reader.
+    Escape(Vec<u8>),
+    Wait(Duration),
+}
+
+/// Drives the LEDs and buzzer of a reader model.
+pub trait Driver {
+    fn steps(&self, signal: Signal) -> Vec<Step>;
+
+    /// Whether the reader passes PN53x commands such as PWD_AUTH through to the card.
+    fn passes_through(&self) -> bool {
+        false
+    }
+}
+
+/// The ACS ACR122U, which combines the LEDs and buzzer in a single pseudo-APDU.
+pub struct Acr122u;
+
+impl Driver for Acr122u {
+    fn steps(&self, signal: Signal) -> Vec<Step> {
+        // Blink the LED once for 100ms with the buzzer on, or twice for a failure.
+        // The LED state control byte masks and blinks only the green or red LED.
+        let (leds, repetitions) = match signal {
+            Signal::Success => (0b1010_1000, 1),
+            Signal::Failure => (0b0101_0100, 2),
+        };
+
+        vec![Step::Escape(vec![
+            0xFF,
+            0x00,
+            0x40,
+            leds,
+            0x04,
+            0x01,
+            0x01,
+            repetitions,
+            0x01,
+        ])]
+    }
+
+    fn passes_through(&self) -> bool {
+        true
+    }
+}
+
+/// The ACS ACR1252U, which controls the LEDs and buzzer with separate escape commands.
+pub struct Acr1252;
+
+impl Acr1252 {
+    const GREEN: u8 = 0b10;
+    const RED: u8 = 0b01;
+
+    fn led(status: u8) -> Step {
+        Step::Escape(vec![0xE0, 0x00, 0x00, 0x29, 0x01, status])
+    }
+
+    /// Sounds the buzzer for the duration in units of 10ms.
+    fn buzzer(duration: u8) -> Step {
+        Step::Escape(vec![0xE0, 0x00, 0x00, 0x28, 0x01, duration])
+    }
+}
+
+impl Driver for Acr1252 {
+    fn steps(&self, signal: Signal) -> Vec<Step> {
+        match signal {
+            Signal::Success => vec![
+                Acr1252::led(Acr1252::GREEN),
+                Acr1252::buzzer(5),
+                Step::Wait(Duration::from_millis(300)),
+                Acr1252::led(0),
+            ],
+            Signal::Failure => vec![
+                Acr1252::led(Acr1252::RED),
+                Acr1252::buzzer(10),
+                Step::Wait(Duration::from_millis(200)),
+                Acr1252::buzzer(10),
+                Step::Wait(Duration::from_millis(300)),
+                Acr1252::led(0),
+            ],
+        }
+    }
+}
+
+/// Readers without known LED or buzzer commands.
+pub struct Silent;
+
+impl Driver for Silent {
+    fn steps(&self, _: Signal) -> Vec<Step> {
+        Vec::new()
+    }
+}
+
+/// The driver for the reader model in the reader name.
+pub fn driver(reader: &str) -> Box<dyn Driver> {
+    if reader.contains("ACR122") {
+        Box::new(Acr122u)
+    } else if reader.contains("ACR1252") {
+        Box::new(Acr1252)
+    } else {
+        Box::new(Silent)
+    }
+}
+
+/// Signals the outcome of a card to the reader that last presented a card to a player.
+#[derive(Clone)]
+pub struct Feedback {
+    last: Arc<Mutex<Option<CString>>>,
+    signals: Sender<(CString, Signal)>,
+}
+
+impl Feedback {
+    pub fn new(signals: Sender<(CString, Signal)>) -> Self {
+        Self {
+            last: Arc::new(Mutex::new(None)),
+            signals,
+        }
+    }
+
+    /// Records the reader a card was presented to.
+    pub fn presented(&self, reader: &CStr) {
+        let mut guard = self.last.lock().unwrap_or_else(PoisonError::into_inner);
+        *guard = Some(reader.to_owned());
+    }
+
+    pub fn signal(&self, signal: Signal) {
+        let reader = self
+            .last
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+
+        if let Some(reader) = reader
+            && let Err(e) = self.signals.send((reader, signal))
+        {
+            tracing::debug!(%e, "Failed to queue the reader feedback");
+        }
+    }
+}
+
+/// Plays the signals on the readers until every feedback handle is dropped.
+/// Failures to signal a reader never stop the jukebox.
+pub fn run(signals: Receiver<(CString, Signal)>) -> anyhow::Result<()> {
+    for (reader, signal) in signals {
+        if let Err(e) = play(&reader, signal) {
+            tracing::debug!(%e, ?reader, ?signal, "Failed to signal the reader");
+        }
+    }
+
+    Ok(())
+}
+
+fn play(reader: &CStr, signal: Signal) -> anyhow::Result<()> {
+    let steps = driver(&reader.to_string_lossy()).steps(signal);
+    if steps.is_empty() {
+        return Ok(());
+    }
+
+    let ctx = Context::establish(pcsc::Scope::User)?;
+    let card = ctx.connect(reader, pcsc::ShareMode::Direct, pcsc::Protocols::UNDEFINED)?;
+    let mut buffer = vec![0; 1024];
+
+    for step in steps {
+        match step {
+            Step::Escape(command) => {
+                card.control(ctl_code(3500), &command, &mut buffer)?;
+            }
+            Step::Wait(duration) => std::thread::sleep(duration),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selects_driver_by_reader_name() {
+        let name = "ACS ACR122U PICC Interface 00 00";
+        assert_eq!(
+            driver(name).steps(Signal::Success),
+            Acr122u.steps(Signal::Success)
+        );
+
+        let name = "ACS ACR1252 1S CL Reader PICC 0";
+        assert_eq!(
+            driver(name).steps(Signal::Failure),
+            Acr1252.steps(Signal::Failure)
+        );
+
+        assert!(driver("Generic Reader").steps(Signal::Success).is_empty());
+    }
+
+    #[test]
+    fn only_acr122u_passes_through() {
+        assert!(driver("ACS ACR122U PICC Interface 00 00").passes_through());
+        assert!(!driver("ACS ACR1252 1S CL Reader PICC 0").passes_through());
+        assert!(!driver("Generic Reader").passes_through());
+    }
+
+    #[test]
+    fn acr122u_blinks_red_twice_on_failure() {
+        let steps = Acr122u.steps(Signal::Failure);
+
+        assert_eq!(
+            steps,
+            vec![Step::Escape(vec![
+                0xFF, 0x00, 0x40, 0x54, 0x04, 0x01, 0x01, 0x02, 0x01
+            ])]
+        );
+    }
+
+    #[test]
+    fn acr1252_beeps_twice_on_failure() {
+        let beeps = Acr1252
+            .steps(Signal::Failure)
+            .into_iter()
+            .filter(|step| matches!(step, Step::Escape(command) if command[3] == 0x28))
+            .count();
+
+        assert_eq!(beeps, 2);
+    }
+
+    #[test]
+    fn feedback_signals_last_reader() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let feedback = Feedback::new(sender);
+
+        feedback.signal(Signal::Success);
+        feedback.presented(c"PICC 0");
+        feedback.presented(c"PICC 1");
+        feedback.signal(Signal::Failure);
+        drop(feedback);
+
+        let signals: Vec<_> = receiver.into_iter().collect();
+        assert_eq!(signals, vec![(c"PICC 1".to_owned(), Signal::Failure)]);
+    }
+}
@@ -0,0 +1,309 @@
+use crate::card::uri;
+use anyhow::anyhow;
+
+// Flags in the first byte of an NDEF record header.
+const MESSAGE_BEGIN: u8 = 0x80;
+const MESSAGE_END: u8 = 0x40;
+const CHUNK: u8 = 0x20;
+const SHORT_RECORD: u8 = 0x10;
+const ID_LENGTH: u8 = 0x08;
+const TNF_MASK: u8 = 0x07;
+
+// Type name formats.
+const TNF_WELL_KNOWN: u8 = 0x01;
+const TNF_UNCHANGED: u8 = 0x06;
+
+// The well-known type of URI records.
+const URI_TYPE: &[u8] = b"U";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Message {
+    pub records: Vec<Record>,
+}
+
+impl Message {
+    /// Parses every record in the message, joining chunked records into a single record.
+    pub fn parse(bytes: &[u8]) -> anyhow::Result<Self> {
+        let mut records = Vec::new();
+        let mut chunked: Option<Record> = None;
+        let mut position = 0;
+
+        while position < bytes.len() {
+            let (record, length) = Record::parse(&bytes[position..])?;
+            let last = record.header & MESSAGE_END != 0;
+
+            position += length;
+
+            match chunked.as_mut() {
+                Some(first) => {
+                    if record.tnf() != TNF_UNCHANGED {
+                        return Err(anyhow!("The chunked NDEF record changed its type"));
+                    }
+
+                    first.payload.extend_from_slice(&record.payload);
+
+                    if record.header & CHUNK == 0 {
+                        first.header &= !CHUNK;
+                        records.extend(chunked.take());
+                    }
+                }
+                None if record.header & CHUNK != 0 => chunked = Some(record),
+                None => records.push(record),
+            }
+
+            if last {
+                break;
+            }
+        }
+
+        if chunked.is_some() {
+            return Err(anyhow!("The chunked NDEF record is truncated"));
+        }
+
+        Ok(Self { records })
+    }
+
+    /// The URIs of every URI record, in the order they appear in the message.
+    pub fn uris(&self) -> impl Iterator<Item = String> + '_ {
+        self.records
+            .iter()
+            .filter_map(|record| match record.to_uri()? {
+                Ok(uri) => Some(uri),
+                Err(e) => {
+                    tracing::warn!(%e, ?record, "Invalid URI record");
+                    None
+                }
+            })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub header: u8,
+    pub record_type: Vec<u8>,
+    pub id: Vec<u8>,
+    pub payload: Vec<u8>,
+}
+
+impl Record {
+    /// A URI record that is the only record in its message.
+    pub fn uri(uri: &str) -> Self {
+        Self {
+            header: MESSAGE_BEGIN | MESSAGE_END | TNF_WELL_KNOWN,
+            record_type: URI_TYPE.to_vec(),
+            id: Vec::new(),
+            payload: uri::encode(uri),
+        }
+    }
+
+    /// Parses the record at the start of the bytes.
+    /// Returns the record and the number of bytes it occupies.
+    pub fn parse(bytes: &[u8]) -> anyhow::Result<(Self, usize)> {
+        let mut cursor = Cursor { bytes, position: 0 };
+
+        let header = cursor.take(1)?[0];
+        let type_length = cursor.take(1)?[0] as usize;
+        let payload_length = if header & SHORT_RECORD != 0 {
+            cursor.take(1)?[0] as usize
+        } else {
+            let length = cursor.take(4)?;
+            u32::from_be_bytes([length[0], length[1], length[2], length[3]]) as usize
+        };
+        let id_length = if header & ID_LENGTH != 0 {
+            cursor.take(1)?[0] as usize
+        } else {
+            0
+        };
+
+        let record = Self {
+            header,
+            record_type: cursor.take(type_length)?.to_vec(),
+            id: cursor.take(id_length)?.to_vec(),
+            payload: cursor.take(payload_length)?.to_vec(),
+        };
+
+        Ok((record, cursor.position))
+    }
+
+    /// Encodes the record, using the short record form when the payload allows it.
+    pub fn encode(&self) -> anyhow::Result<Vec<u8>> {
+        let mut header = self.header & !(SHORT_RECORD | ID_LENGTH);
+        if self.payload.len() <= u8::MAX as usize {
+            header |= SHORT_RECORD;
+        }
+        if !self.id.is_empty() {
+            header |= ID_LENGTH;
+        }
+
+        let mut bytes = Vec::with_capacity(self.payload.len() + self.record_type.len() + 7);
+        bytes.push(header);
+        bytes.push(u8::try_from(self.record_type.len())?);
+
+        if header & SHORT_RECORD != 0 {
+            bytes.push(self.payload.len() as u8);
+        } else {
+            bytes.extend_from_slice(&u32::try_from(self.payload.len())?.to_be_bytes());
+        }
+        if header & ID_LENGTH != 0 {
+            bytes.push(u8::try_from(self.id.len())?);
+        }
+
+        bytes.extend_from_slice(&self.record_type);
+        bytes.extend_from_slice(&self.id);
+        bytes.extend_from_slice(&self.payload);
+
+        Ok(bytes)
+    }
+
+    pub fn tnf(&self) -> u8 {
+        self.header & TNF_MASK
+    }
+
+    /// The URI of a URI record, or none for any other type of record.
+    pub fn to_uri(&self) -> Option<anyhow::Result<String>> {
+        (self.tnf() == TNF_WELL_KNOWN && self.record_type == URI_TYPE)
+            .then(|| uri::decode(&self.payload))
+    }
+}
+
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, length: usize) -> anyhow::Result<&'a [u8]> {
+        let end = self
+            .position
+            .checked_add(length)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| anyhow!("The NDEF record is truncated"))?;
+        let slice = &self.bytes[self.position..end];
+
+        self.position = end;
+
+        Ok(slice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_short_uri_record() {
+        let bytes = b"\xD1\x01\x0A\x55\x04spotify.c";
+        let (record, length) = Record::parse(bytes).unwrap();
+
+        assert_eq!(length, bytes.len());
+        assert_eq!(record.tnf(), TNF_WELL_KNOWN);
+        assert_eq!(record.to_uri().unwrap().unwrap(), "https://spotify.c");
+    }
+
+    #[test]
+    fn parses_long_uri_record() {
+        let uri = format!("https://open.spotify.com/playlist/1?{}", "a".repeat(300));
+        let payload = uri::encode(&uri);
+        let mut bytes = vec![0xC1, 0x01];
+        bytes.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        bytes.push(0x55);
+        bytes.extend_from_slice(&payload);
+
+        let (record, length) = Record::parse(&bytes).unwrap();
+
+        assert_eq!(length, bytes.len());
+        assert_eq!(record.to_uri().unwrap().unwrap(), uri);
+    }
+
+    #[test]
+    fn parses_record_with_id() {
+        let bytes = b"\xD9\x01\x02\x01\x55\x07\x00a";
+        let (record, _) = Record::parse(bytes).unwrap();
+
+        assert_eq!(record.id, b"\x07");
+        assert_eq!(record.payload, b"\x00a");
+    }
+
+    #[test]
+    fn rejects_truncated_record() {
+        assert!(Record::parse(b"\xD1\x01\x0A\x55\x04spot").is_err());
+        assert!(Record::parse(b"\xC1\x01\x00\x00").is_err());
+    }
+
+    #[test]
+    fn parses_message_with_multiple_records() {
+        let mut bytes = b"\x91\x01\x08\x54\x02enHello".to_vec();
+        bytes.extend_from_slice(b"\x11\x01\x0A\x55\x04spotify.c");
+        bytes.extend_from_slice(b"\x54\x0F\x03android.com:pkgabc");
+
+        let message = Message::parse(&bytes).unwrap();
+
+        assert_eq!(message.records.len(), 3);
+        assert_eq!(message.records[0].record_type, b"T");
+        assert_eq!(message.records[2].tnf(), 0x04);
+        assert_eq!(
+            message.uris().collect::<Vec<_>>(),
+            vec!["https://spotify.c".to_string()]
+        );
+    }
+
+    #[test]
+    fn parses_chunked_record() {
+        let mut bytes = b"\xB1\x01\x04\x55\x00spo".to_vec();
+        bytes.extend_from_slice(b"\x36\x00\x04tify");
+        bytes.extend_from_slice(b"\x56\x00\x0A:track:123");
+
+        let message = Message::parse(&bytes).unwrap();
+
+        assert_eq!(message.records.len(), 1);
+        assert_eq!(message.records[0].header & CHUNK, 0);
+        assert_eq!(
+            message.uris().collect::<Vec<_>>(),
+            vec!["spotify:track:123".to_string()]
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_chunked_record() {
+        assert!(Message::parse(b"\xB1\x01\x04\x55\x00spo").is_err());
+        assert!(Message::parse(b"\xB1\x01\x04\x55\x00spo\x51\x00\x01a").is_err());
+    }
+
+    #[test]
+    fn stops_at_message_end() {
+        let mut bytes = b"\xD1\x01\x0A\x55\x04spotify.c".to_vec();
+        bytes.extend_from_slice(b"\x00\x00\x00\x00");
+
+        let message = Message::parse(&bytes).unwrap();
+
+        assert_eq!(message.records.len(), 1);
+    }
+
+    #[test]
+    fn skips_invalid_uri_records() {
+        let mut bytes = b"\x91\x01\x02\x55\x30a".to_vec();
+        bytes.extend_from_slice(b"\x51\x01\x02\x55\x00a");
+
+        let message = Message::parse(&bytes).unwrap();
+
+        assert_eq!(message.uris().collect::<Vec<_>>(), vec!["a".to_string()]);
+    }
+
+    #[test]
+    fn encodes_short_and_long_records() {
+        let short = Record::uri("spotify:track:1");
+        assert_eq!(short.encode().unwrap()[0], 0xD1);
+
+        let long = Record::uri(&format!("spotify:track:{}", "1".repeat(300)));
+        let bytes = long.encode().unwrap();
+        assert_eq!(bytes[0], 0xC1);
+
+        for record in [short, long] {
+            let bytes = record.encode().unwrap();
+            let (parsed, length) = Record::parse(&bytes).unwrap();
+
+            assert_eq!(length, bytes.len());
+            assert_eq!(parsed.payload, record.payload);
+        }
+    }
+}
@@ -0,0 +1,420 @@
+use crate::card::transport::Transport;
+use crate::card::{TagType, read_ndef};
+use anyhow::anyhow;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+// The page of the static lock bytes.
+const STATIC_LOCK_PAGE: u8 = 0x02;
+// The page of the capability container.
+const CC_PAGE: u8 = 0x03;
+// The access byte of a capability container that is read-only.
+const READ_ONLY: u8 = 0x0F;
+// The first page of user memory, protected by the password when finalizing.
+const FIRST_USER_PAGE: u8 = 0x04;
+// The password acknowledge returned by cards protected by the jukebox.
+const PACK: [u8; 2] = *b"JB";
+// The PWD_AUTH command, sent through the PN53x InCommunicateThru pass-through of the ACR122U.
+const PWD_AUTH: &[u8] = b"\xFF\x00\x00\x00\x07\xD4\x42\x1B";
+
+/// The NTAG21x variants, identified by the data area size in the capability container.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variant {
+    Ntag213,
+    Ntag215,
+    Ntag216,
+}
+
+impl Variant {
+    fn from_capabilities(cc: &[u8]) -> Option<Self> {
+        match cc.get(2)? {
+            0x12 => Some(Variant::Ntag213),
+            0x3E => Some(Variant::Ntag215),
+            0x6D => Some(Variant::Ntag216),
+            _ => None,
+        }
+    }
+
+    /// The page with the dynamic lock bytes, followed by the CFG0, CFG1, PWD and PACK pages.
+    fn dynamic_lock_page(self) -> u8 {
+        match self {
+            Variant::Ntag213 => 0x28,
+            Variant::Ntag215 => 0x82,
+            Variant::Ntag216 => 0xE2,
+        }
+    }
+
+    /// The page with AUTH0, the first page that requires the password.
+    fn cfg0_page(self) -> u8 {
+        self.dynamic_lock_page() + 1
+    }
+
+    fn pwd_page(self) -> u8 {
+        self.dynamic_lock_page() + 3
+    }
+
+    fn pack_page(self) -> u8 {
+        self.dynamic_lock_page() + 4
+    }
+}
+
+/// The 32-bit password of an NTAG21x tag, written as 8 hexadecimal digits.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Password([u8; 4]);
+
+impl FromStr for Password {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value = u32::from_str_radix(s, 16)
+            .ok()
+            .filter(|_| s.len() == 8 && s.bytes().all(|c| c.is_ascii_hexdigit()))
+            .ok_or_else(|| anyhow!("Invalid password, expected 8 hexadecimal digits"))?;
+
+        Ok(Password(value.to_be_bytes()))
+    }
+}
+
+impl std::fmt::Debug for Password {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Password(********)")
+    }
+}
+
+/// How to protect a card against being overwritten.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Finalize {
+    /// Make the card read-only, which cannot be undone.
+    pub lock: bool,
+    /// Require the password to write to the card.
+    pub protect: bool,
+    /// Only report what would be written.
+    pub dry_run: bool,
+}
+
+/// A write to a page of the tag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Write {
+    pub page: u8,
+    pub data: [u8; 4],
+    pub description: String,
+}
+
+/// The writes that finalize a tag, in the order they are made.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plan {
+    pub variant: Variant,
+    pub writes: Vec<Write>,
+}
+
+impl Display for Plan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}:", self.variant)?;
+
+        for write in &self.writes {
+            write!(
+                f,
+                " page {:02X} = {:02X?} ({});",
+                write.page, write.data, write.description
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Plans the writes that lock the tag and protect it with the password.
+/// Locking comes first, since the dynamic lock bytes are in user memory once it is protected.
+pub(super) fn plan(
+    transport: &mut impl Transport,
+    finalize: Finalize,
+    password: Option<&Password>,
+) -> anyhow::Result<Plan> {
+    let cc = read_page(transport, CC_PAGE)?;
+    let variant = Variant::from_capabilities(&cc)
+        .ok_or_else(|| anyhow!("Only NTAG213, NTAG215 and NTAG216 tags can be finalized"))?;
+    let mut writes = Vec::new();
+
+    if finalize.lock {
+        // Locking cannot be undone, so the card has to play before it is locked.
+        let message = read_ndef(transport, TagType::Type2)
+            .map_err(|e| anyhow!("The NDEF message on the card is invalid: {e}"))?;
+        if message.uris().next().is_none() {
+            return Err(anyhow!("The card holds no URI, so locking it would leave it useless"));
+        }
+
+        let dynamic = variant.dynamic_lock_page();
+
+        writes.push(Write {
+            page: CC_PAGE,
+            data: [cc[0], cc[1], cc[2], READ_ONLY],
+            description: "make the capability container read-only".to_string(),
+        });
+        writes.push(Write {
+            page: dynamic,
+            data: [0xFF, 0xFF, 0xFF, 0x00],
+            description: format!("lock pages 10 to {:02X}", dynamic - 1),
+        });
+        // The first two bytes of the page hold the UID and cannot be written.
+        writes.push(Write {
+            page: STATIC_LOCK_PAGE,
+            data: [0x00, 0x00, 0xFF, 0xFF],
+            description: "lock pages 03 to 0F".to_string(),
+        });
+    }
+
+    if finalize.protect {
+        let Password(password) =
+            password.ok_or_else(|| anyhow!("Protecting the card requires a card password"))?;
+        let cfg0 = read_page(transport, variant.cfg0_page())?;
+
+        writes.push(Write {
+            page: variant.pwd_page(),
+            data: *password,
+            description: "set the password".to_string(),
+        });
+        writes.push(Write {
+            page: variant.pack_page(),
+            data: [PACK[0], PACK[1], 0x00, 0x00],
+            description: "set the password acknowledge".to_string(),
+        });
+        writes.push(Write {
+            page: variant.cfg0_page(),
+            data: [cfg0[0], cfg0[1], cfg0[2], FIRST_USER_PAGE],
+            description: format!("require the password to write from page {FIRST_USER_PAGE:02X}"),
+        });
+    }
+
+    Ok(Plan { variant, writes })
+}
+
+pub(super) fn apply(transport: &mut impl Transport, plan: &Plan) -> anyhow::Result<()> {
+    for write in &plan.writes {
+        let mut command = vec![0xFF, 0xD6, 0x00, write.page, 0x04];
+        command.extend_from_slice(&write.data);
+
+        transport.execute(&command, &format!("write page {:02X}", write.page))?;
+        tracing::debug!(page = write.page, description = %write.description, "Finalized");
+    }
+
+    Ok(())
+}
+
+/// Authenticates with the password when the tag requires it for writing,
+/// which only readers that pass PN53x commands through to the card can do.
+/// Tags that are not NTAG21x or are not protected are left as they are.
+pub(super) fn unlock(
+    transport: &mut impl Transport,
+    password: &Password,
+    passes_through: bool,
+) -> anyhow::Result<()> {
+    let cc = read_page(transport, CC_PAGE)?;
+    let Some(variant) = Variant::from_capabilities(&cc) else {
+        return Ok(());
+    };
+
+    // AUTH0 beyond the last page disables the password.
+    let auth0 = read_page(transport, variant.cfg0_page())?[3];
+    if auth0 > variant.pack_page() {
+        return Ok(());
+    }
+
+    if !passes_through {
+        return Err(anyhow!(
+            "The card is protected by a password, which only the ACR122U can authenticate with"
+        ));
+    }
+
+    authenticate(transport, password)
+}
+
+fn authenticate(
+    transport: &mut impl Transport,
+    Password(password): &Password,
+) -> anyhow::Result<()> {
+    let command = [PWD_AUTH, password].concat();
+    let response = transport.execute(&command, "authenticate with the password")?;
+
+    match response.as_slice() {
+        [0xD5, 0x43, 0x00, pack @ ..] if *pack == PACK => Ok(()),
+        [0xD5, 0x43, 0x00, ..] => Err(anyhow!("The card was not protected by the jukebox")),
+        _ => Err(anyhow!("The password was rejected")),
+    }
+}
+
+fn read_page(transport: &mut impl Transport, page: u8) -> anyhow::Result<Vec<u8>> {
+    let data = transport.execute(
+        &[0xFF, 0xB0, 0x00, page, 0x04],
+        &format!("read page {page:02X}"),
+    )?;
+
+    data.get(..4)
+        .map(<[u8]>::to_vec)
+        .ok_or_else(|| anyhow!("The read operation returned too few bytes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::{SUCCESS, encode_uri};
+
+    /// Serves reads and records writes on the pages of an NTAG213 with the password disabled
+    /// and a track URI in user memory.
+    fn ntag213(pages: &mut [[u8; 4]; 45]) -> impl FnMut(&[u8]) -> anyhow::Result<Vec<u8>> + '_ {
+        pages[3] = [0xE1, 0x10, 0x12, 0x00];
+        pages[0x29] = [0x04, 0x00, 0x00, 0xFF];
+        let message = encode_uri("spotify:track:123").unwrap();
+        for (page, chunk) in pages[4..].iter_mut().zip(message.chunks(4)) {
+            page[..chunk.len()].copy_from_slice(chunk);
+        }
+
+        move |command| {
+            let page = command[3] as usize;
+
+            match command[..2] {
+                [0xFF, 0xB0] => {
+                    let count = command[4] as usize / 4;
+                    Ok([pages[page..page + count].concat().as_slice(), SUCCESS].concat())
+                }
+                [0xFF, 0xD6] => {
+                    pages[page].copy_from_slice(&command[5..9]);
+                    Ok(SUCCESS.to_vec())
+                }
+                _ => Err(anyhow!("Unsupported command")),
+            }
+        }
+    }
+
+    #[test]
+    fn parses_passwords() {
+        let password: Password = "1a2B3c4D".parse().unwrap();
+
+        assert_eq!(password, Password([0x1A, 0x2B, 0x3C, 0x4D]));
+        assert_eq!(format!("{password:?}"), "Password(********)");
+        assert!("1A2B3C".parse::<Password>().is_err());
+        assert!("+1A2B3C4".parse::<Password>().is_err());
+    }
+
+    #[test]
+    fn plans_locking_before_protecting() {
+        let mut pages = [[0; 4]; 45];
+        let password = Password([1, 2, 3, 4]);
+        let finalize = Finalize {
+            lock: true,
+            protect: true,
+            dry_run: false,
+        };
+
+        let plan = plan(&mut ntag213(&mut pages), finalize, Some(&password)).unwrap();
+
+        assert_eq!(plan.variant, Variant::Ntag213);
+        let writes: Vec<(u8, [u8; 4])> = plan.writes.iter().map(|w| (w.page, w.data)).collect();
+        assert_eq!(
+            writes,
+            vec![
+                (0x03, [0xE1, 0x10, 0x12, 0x0F]),
+                (0x28, [0xFF, 0xFF, 0xFF, 0x00]),
+                (0x02, [0x00, 0x00, 0xFF, 0xFF]),
+                (0x2B, [1, 2, 3, 4]),
+                (0x2C, [b'J', b'B', 0x00, 0x00]),
+                (0x29, [0x04, 0x00, 0x00, 0x04]),
+            ]
+        );
+    }
+
+    #[test]
+    fn planning_does_not_write() {
+        let mut pages = [[0; 4]; 45];
+        let finalize = Finalize {
+            lock: true,
+            ..Finalize::default()
+        };
+
+        plan(&mut ntag213(&mut pages), finalize, None).unwrap();
+
+        assert_eq!(pages[2], [0; 4]);
+        assert_eq!(pages[0x28], [0; 4]);
+    }
+
+    #[test]
+    fn protecting_requires_a_password() {
+        let mut pages = [[0; 4]; 45];
+        let finalize = Finalize {
+            protect: true,
+            ..Finalize::default()
+        };
+
+        assert!(plan(&mut ntag213(&mut pages), finalize, None).is_err());
+    }
+
+    #[test]
+    fn locking_requires_a_uri() {
+        let mut pages = [[0; 4]; 45];
+        let mut tag = ntag213(&mut pages);
+        let mut blank = |command: &[u8]| match command {
+            // An empty NDEF message TLV followed by a terminator TLV.
+            [0xFF, 0xB0, 0x00, 0x04, _] => Ok(b"\x03\x00\xFE\x00\x90\x00".to_vec()),
+            _ => tag(command),
+        };
+        let finalize = Finalize {
+            lock: true,
+            ..Finalize::default()
+        };
+
+        let error = plan(&mut blank, finalize, None).unwrap_err();
+
+        assert!(error.to_string().contains("no URI"), "{error}");
+    }
+
+    #[test]
+    fn unlocks_only_protected_tags() {
+        let password = Password([1, 2, 3, 4]);
+        let mut pages = [[0; 4]; 45];
+        let mut commands = 0;
+        let mut tag = ntag213(&mut pages);
+        let mut counted = |command: &[u8]| {
+            commands += 1;
+            tag(command)
+        };
+
+        unlock(&mut counted, &password, false).unwrap();
+        assert_eq!(commands, 2);
+
+        let mut authenticated = Vec::new();
+        let mut protected = |command: &[u8]| -> anyhow::Result<Vec<u8>> {
+            authenticated.push(command.to_vec());
+            match command[..2] {
+                [0xFF, 0xB0] if command[3] == 3 => Ok(b"\xE1\x10\x3E\x00\x90\x00".to_vec()),
+                [0xFF, 0xB0] => Ok(b"\x04\x00\x00\x04\x90\x00".to_vec()),
+                _ => Ok(b"\xD5\x43\x00JB\x90\x00".to_vec()),
+            }
+        };
+
+        // Readers without the pass-through are refused before sending the password.
+        assert!(unlock(&mut protected, &password, false).is_err());
+        unlock(&mut protected, &password, true).unwrap();
+        assert_eq!(authenticated.len(), 5);
+        assert_eq!(authenticated[3], b"\xFF\xB0\x00\x83\x04");
+        assert_eq!(
+            authenticated[4],
+            b"\xFF\x00\x00\x00\x07\xD4\x42\x1B\x01\x02\x03\x04"
+        );
+    }
+
+    #[test]
+    fn applies_writes_in_order() {
+        let mut pages = [[0; 4]; 45];
+        let mut tag = ntag213(&mut pages);
+        let finalize = Finalize {
+            lock: true,
+            ..Finalize::default()
+        };
+
+        let plan = plan(&mut tag, finalize, None).unwrap();
+        apply(&mut tag, &plan).unwrap();
+        drop(tag);
+
+        assert_eq!(pages[3], [0xE1, 0x10, 0x12, 0x0F]);
+        assert_eq!(pages[2], [0x00, 0x00, 0xFF, 0xFF]);
+    }
+}
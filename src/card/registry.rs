@@ -0,0 +1,111 @@
+use crate::store::Store;
+use anyhow::anyhow;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, PoisonError, RwLock};
+
+/// Maps card UIDs to the URI the card plays, so cards without NDEF content can be used.
+#[derive(Clone, Default)]
+pub struct Registry {
+    store: Store,
+    cards: Arc<RwLock<BTreeMap<String, String>>>,
+}
+
+impl Registry {
+    /// Loads the registry from the file, if any.
+    /// A missing file is treated as an empty registry.
+    pub fn load(path: Option<PathBuf>) -> anyhow::Result<Self> {
+        let store = Store::new(path);
+        let cards = store.load()?;
+
+        Ok(Self {
+            store,
+            cards: Arc::new(RwLock::new(cards)),
+        })
+    }
+
+    pub fn get(&self, uid: &str) -> Option<String> {
+        let guard = self.cards.read().unwrap_or_else(PoisonError::into_inner);
+        guard.get(uid).cloned()
+    }
+
+    pub fn cards(&self) -> BTreeMap<String, String> {
+        self.cards
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Binds the card to the URI and persists the registry.
+    /// An empty URI removes the binding. The registry only changes once it is saved.
+    pub fn bind(&self, uid: String, uri: String) -> anyhow::Result<()> {
+        let uid = normalize_uid(&uid)?;
+        let mut guard = self.cards.write().unwrap_or_else(PoisonError::into_inner);
+        let mut cards = guard.clone();
+
+        if uri.is_empty() {
+            cards.remove(&uid);
+        } else {
+            cards.insert(uid, uri);
+        }
+
+        self.store.save(&cards)?;
+        *guard = cards;
+
+        Ok(())
+    }
+}
+
+/// Formats the UID as uppercase hexadecimal like the readers report it,
+/// dropping separators such as in `04:a2:5f:1c`.
+pub(super) fn normalize_uid(uid: &str) -> anyhow::Result<String> {
+    let digits: String = uid
+        .chars()
+        .filter(|c| !matches!(c, ':' | '-' | ' '))
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+
+    if digits.is_empty()
+        || !digits.len().is_multiple_of(2)
+        || !digits.chars().all(|c| c.is_ascii_hexdigit())
+    {
+        return Err(anyhow!("Invalid UID {uid:?}, expected hexadecimal bytes"));
+    }
+
+    Ok(digits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Registry, normalize_uid};
+
+    #[test]
+    fn binds_and_unbinds_cards() {
+        let registry = Registry::default();
+
+        registry
+            .bind("04A1B2C3".to_string(), "spotify:album:1".to_string())
+            .unwrap();
+        assert_eq!(registry.get("04A1B2C3").as_deref(), Some("spotify:album:1"));
+
+        registry
+            .bind("04A1B2C3".to_string(), String::new())
+            .unwrap();
+        assert_eq!(registry.get("04A1B2C3"), None);
+    }
+
+    #[test]
+    fn normalizes_uids() {
+        let registry = Registry::default();
+
+        registry
+            .bind("04:a2:5f:1c".to_string(), "spotify:album:1".to_string())
+            .unwrap();
+        assert_eq!(registry.get("04A25F1C").as_deref(), Some("spotify:album:1"));
+
+        assert_eq!(normalize_uid("04-A2 5F1c").unwrap(), "04A25F1C");
+        assert!(normalize_uid("").is_err());
+        assert!(normalize_uid("04A").is_err());
+        assert!(normalize_uid("04:G2").is_err());
+    }
+}
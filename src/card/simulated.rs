@@ -0,0 +1,233 @@
+use crate::card::ndef::{Message, Record};
+use crate::card::registry::normalize_uid;
+use crate::card::{Finalize, Plan, Source, Tag};
+use crate::player::parse_duration;
+use anyhow::anyhow;
+use std::ffi::{CStr, CString};
+use std::io::BufRead;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+// The name of the simulated reader.
+const READER: &CStr = c"Virtual Reader";
+
+/// A line of a simulation script.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Step {
+    /// Insert a card with the URI and optional UID, replacing any card in the reader.
+    Insert {
+        uri: String,
+        uid: Option<String>,
+    },
+    Remove,
+    Wait(Duration),
+}
+
+impl Step {
+    /// Parses a line such as `insert spotify:album:1`, `remove` or `wait 5s`.
+    /// Blank lines and comments starting with `#` have no step.
+    fn parse(line: &str) -> anyhow::Result<Option<Step>> {
+        let mut words = line.split_whitespace();
+
+        match (words.next(), words.next(), words.next(), words.next()) {
+            (None, ..) => Ok(None),
+            (Some(word), ..) if word.starts_with('#') => Ok(None),
+            (Some("insert"), Some(uri), uid, None) => Ok(Some(Step::Insert {
+                uri: uri.to_string(),
+                uid: uid.map(normalize_uid).transpose()?,
+            })),
+            (Some("remove"), None, ..) => Ok(Some(Step::Remove)),
+            (Some("wait"), Some(duration), None, _) => {
+                Ok(Some(Step::Wait(parse_duration(duration)?)))
+            }
+            _ => Err(anyhow!("Unknown step {line:?}")),
+        }
+    }
+}
+
+/// A single virtual reader driven by a script of steps, one per line:
+/// `insert <uri> [<uid>]`, `remove` and `wait <duration>`.
+/// Lets the jukebox run without a card reader, reading the script from a file or stdin.
+pub struct Simulated {
+    lines: Receiver<std::io::Result<String>>,
+    tag: Option<Tag>,
+    // The end of the current wait step.
+    resume: Option<Instant>,
+}
+
+impl Simulated {
+    /// Reads the script on its own thread, so waiting for the next step can time out.
+    pub fn new(script: impl BufRead + Send + 'static) -> Self {
+        let (sender, lines) = std::sync::mpsc::channel();
+
+        std::thread::spawn(move || {
+            for line in script.lines() {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            lines,
+            tag: None,
+            resume: None,
+        }
+    }
+}
+
+impl Source for Simulated {
+    fn names(&self) -> Vec<String> {
+        vec![READER.to_string_lossy().into_owned()]
+    }
+
+    fn wait(&mut self, timeout: Option<Duration>) -> anyhow::Result<Option<Vec<CString>>> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+        loop {
+            // Finish the current wait step, unless the timeout elapses first.
+            if let Some(resume) = self.resume {
+                let until = deadline.map_or(resume, |deadline| deadline.min(resume));
+                std::thread::sleep(until.saturating_duration_since(Instant::now()));
+
+                if until < resume {
+                    return Ok(Some(Vec::new()));
+                }
+                self.resume = None;
+            }
+
+            let received = match deadline {
+                None => self
+                    .lines
+                    .recv()
+                    .map_err(|_| RecvTimeoutError::Disconnected),
+                Some(deadline) => self
+                    .lines
+                    .recv_timeout(deadline.saturating_duration_since(Instant::now())),
+            };
+            let line = match received {
+                Ok(line) => line?,
+                Err(RecvTimeoutError::Timeout) => return Ok(Some(Vec::new())),
+                Err(RecvTimeoutError::Disconnected) => return Ok(None),
+            };
+
+            // A mistyped step should not stop an interactive simulation.
+            let step = match Step::parse(&line) {
+                Ok(Some(step)) => step,
+                Ok(None) => continue,
+                Err(e) => {
+                    tracing::warn!(%e, "Skipping the step");
+                    continue;
+                }
+            };
+
+            tracing::debug!(?step, "Simulating");
+
+            match step {
+                Step::Insert { uri, uid } => {
+                    self.tag = Some(Tag {
+                        reader: READER.to_owned(),
+                        uid,
+                        message: Message {
+                            records: vec![Record::uri(&uri)],
+                        },
+                    });
+
+                    return Ok(Some(vec![READER.to_owned()]));
+                }
+                Step::Remove => {
+                    if self.tag.take().is_some() {
+                        return Ok(Some(vec![READER.to_owned()]));
+                    }
+                }
+                Step::Wait(duration) => self.resume = Some(Instant::now() + duration),
+            }
+        }
+    }
+
+    fn read(&self, reader: &CStr) -> anyhow::Result<Option<Tag>> {
+        Ok(self.tag.clone().filter(|_| reader == READER))
+    }
+
+    fn write(&self, reader: &CStr, uri: &str) -> anyhow::Result<bool> {
+        // The script decides what the next card holds, so the write is not kept.
+        tracing::info!(%uri, ?reader, "Simulated writing the URI to the card");
+
+        Ok(self.tag.is_some() && reader == READER)
+    }
+
+    fn finalize(&self, _: &CStr, _: Finalize) -> anyhow::Result<Option<Plan>> {
+        Err(anyhow!("The virtual reader cannot finalize cards"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_steps() {
+        assert_eq!(
+            Step::parse("insert spotify:album:1").unwrap(),
+            Some(Step::Insert {
+                uri: "spotify:album:1".to_string(),
+                uid: None,
+            })
+        );
+        assert_eq!(
+            Step::parse("insert file:///stories 04a1b2c3").unwrap(),
+            Some(Step::Insert {
+                uri: "file:///stories".to_string(),
+                uid: Some("04A1B2C3".to_string()),
+            })
+        );
+        assert_eq!(Step::parse("  remove ").unwrap(), Some(Step::Remove));
+        assert_eq!(
+            Step::parse("wait 5s").unwrap(),
+            Some(Step::Wait(Duration::from_secs(5)))
+        );
+        assert_eq!(Step::parse("").unwrap(), None);
+        assert_eq!(Step::parse("# a comment").unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_unknown_steps() {
+        assert!(Step::parse("eject").is_err());
+        assert!(Step::parse("insert").is_err());
+        assert!(Step::parse("remove now").is_err());
+        assert!(Step::parse("wait").is_err());
+        assert!(Step::parse("wait 5").is_err());
+    }
+
+    #[test]
+    fn replays_script() {
+        let script =
+            "insert spotify:album:1\nwait 0s\nbogus\nremove\nremove\ninsert jukebox:skip\n";
+        let mut source = Simulated::new(script.as_bytes());
+
+        assert_eq!(source.wait(None).unwrap(), Some(vec![READER.to_owned()]));
+        let tag = source.read(READER).unwrap().unwrap();
+        assert_eq!(
+            tag.message.uris().collect::<Vec<_>>(),
+            vec!["spotify:album:1"]
+        );
+
+        assert_eq!(source.wait(None).unwrap(), Some(vec![READER.to_owned()]));
+        assert!(source.read(READER).unwrap().is_none());
+
+        assert_eq!(source.wait(None).unwrap(), Some(vec![READER.to_owned()]));
+        let tag = source.read(READER).unwrap().unwrap();
+        assert_eq!(tag.message.uris().collect::<Vec<_>>(), vec!["jukebox:skip"]);
+
+        assert_eq!(source.wait(None).unwrap(), None);
+    }
+
+    #[test]
+    fn times_out_during_wait_step() {
+        let mut source = Simulated::new("wait 1h\ninsert spotify:album:1\n".as_bytes());
+
+        let toggled = source.wait(Some(Duration::from_millis(10))).unwrap();
+
+        assert_eq!(toggled, Some(Vec::new()));
+    }
+}
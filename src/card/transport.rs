@@ -0,0 +1,202 @@
+use crate::card::SUCCESS;
+use anyhow::anyhow;
+use std::fmt::Write;
+
+/// Exchanges APDUs with a card.
+pub trait Transport {
+    /// Sends the command and returns the response, including the status word.
+    fn transmit(&mut self, command: &[u8]) -> anyhow::Result<Vec<u8>>;
+
+    /// Sends the command and returns the response data when it succeeds.
+    fn execute(&mut self, command: &[u8], operation: &str) -> anyhow::Result<Vec<u8>> {
+        let response = self.transmit(command)?;
+
+        match response.strip_suffix(SUCCESS) {
+            Some(data) => Ok(data.to_vec()),
+            None => Err(anyhow!("Failed to {operation}: {response:02X?}")),
+        }
+    }
+}
+
+impl Transport for pcsc::Card {
+    fn transmit(&mut self, command: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let mut buffer = [0; pcsc::MAX_BUFFER_SIZE];
+        Ok(pcsc::Card::transmit(self, command, &mut buffer)?.to_vec())
+    }
+}
+
+impl<F> Transport for F
+where
+    F: FnMut(&[u8]) -> anyhow::Result<Vec<u8>>,
+{
+    fn transmit(&mut self, command: &[u8]) -> anyhow::Result<Vec<u8>> {
+        self(command)
+    }
+}
+
+/// Records the exchanges with a card as a fixture that can be replayed in tests.
+pub struct Recording<T> {
+    inner: T,
+    exchanges: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+impl<T: Transport> Recording<T> {
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            exchanges: Vec::new(),
+        }
+    }
+
+    /// The recorded exchanges, with each command on a line starting with `>`
+    /// followed by its response on a line starting with `<`.
+    pub fn fixture(&self) -> String {
+        let mut fixture = String::new();
+
+        for (command, response) in &self.exchanges {
+            let _ = writeln!(fixture, "> {}", encode_hex(command));
+            let _ = writeln!(fixture, "< {}", encode_hex(response));
+        }
+
+        fixture
+    }
+}
+
+impl<T: Transport> Transport for Recording<T> {
+    fn transmit(&mut self, command: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let response = self.inner.transmit(command)?;
+        self.exchanges.push((command.to_vec(), response.clone()));
+        Ok(response)
+    }
+}
+
+pub(super) fn encode_hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{byte:02X}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    /// Serves the responses of a recorded fixture, checking each command against the recording.
+    pub struct Replay {
+        exchanges: VecDeque<(Vec<u8>, Vec<u8>)>,
+    }
+
+    impl Replay {
+        /// Parses a fixture in the format written by [`Recording::fixture`].
+        /// Blank lines and comments starting with `#` are ignored.
+        pub fn parse(fixture: &str) -> anyhow::Result<Self> {
+            let mut exchanges = VecDeque::new();
+            let mut command = None;
+
+            for line in fixture.lines().map(str::trim) {
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+
+                match (line.split_at_checked(1), command.take()) {
+                    (Some((">", hex)), None) => command = Some(decode_hex(hex)?),
+                    (Some(("<", hex)), Some(command)) => {
+                        exchanges.push_back((command, decode_hex(hex)?))
+                    }
+                    _ => return Err(anyhow!("Unexpected line in the fixture: {line:?}")),
+                }
+            }
+
+            if command.is_some() {
+                return Err(anyhow!("The last command in the fixture has no response"));
+            }
+
+            Ok(Self { exchanges })
+        }
+
+        /// Whether every recorded exchange has been replayed.
+        pub fn is_finished(&self) -> bool {
+            self.exchanges.is_empty()
+        }
+    }
+
+    impl Transport for Replay {
+        fn transmit(&mut self, command: &[u8]) -> anyhow::Result<Vec<u8>> {
+            match self.exchanges.pop_front() {
+                Some((expected, response)) if expected == command => Ok(response),
+                Some((expected, _)) => Err(anyhow!(
+                    "Expected the command {}, got {}",
+                    encode_hex(&expected),
+                    encode_hex(command)
+                )),
+                None => Err(anyhow!("Unexpected command {}", encode_hex(command))),
+            }
+        }
+    }
+
+    pub fn decode_hex(hex: &str) -> anyhow::Result<Vec<u8>> {
+        let digits: Vec<u8> = hex.bytes().filter(|c| !c.is_ascii_whitespace()).collect();
+
+        digits
+            .chunks(2)
+            .map(|pair| {
+                std::str::from_utf8(pair)
+                    .ok()
+                    .filter(|pair| pair.len() == 2)
+                    .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                    .ok_or_else(|| anyhow!("Invalid hexadecimal in the fixture: {hex:?}"))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn records_and_replays_exchanges() {
+        let mut recording = Recording::new(|command: &[u8]| -> anyhow::Result<Vec<u8>> {
+            let mut response = command.to_vec();
+            response.extend_from_slice(b"\x90\x00");
+            Ok(response)
+        });
+
+        recording.transmit(b"\xFF\xCA\x00\x00\x00").unwrap();
+        recording.transmit(b"\xFF\xB0\x00\x04\x04").unwrap();
+
+        let fixture = recording.fixture();
+        assert_eq!(
+            fixture.lines().next(),
+            Some("> FF CA 00 00 00"),
+            "{fixture}"
+        );
+
+        let mut replay = Replay::parse(&fixture).unwrap();
+        assert_eq!(
+            replay.transmit(b"\xFF\xCA\x00\x00\x00").unwrap(),
+            b"\xFF\xCA\x00\x00\x00\x90\x00"
+        );
+        assert!(!replay.is_finished());
+        assert!(replay.transmit(b"\xFF\xB0\x00\x05\x04").is_err());
+        assert!(replay.is_finished());
+        assert!(replay.transmit(b"\xFF\xB0\x00\x04\x04").is_err());
+    }
+
+    #[test]
+    fn parses_fixture_comments_and_spacing() {
+        let fixture = "# A comment\n\n>FFCA000000\n<  04A1 B2C3 9000\n";
+        let mut replay = Replay::parse(fixture).unwrap();
+
+        assert_eq!(
+            replay.transmit(b"\xFF\xCA\x00\x00\x00").unwrap(),
+            b"\x04\xA1\xB2\xC3\x90\x00"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_fixtures() {
+        assert!(Replay::parse("< 90 00").is_err());
+        assert!(Replay::parse("> FF CA\n> FF CA").is_err());
+        assert!(Replay::parse("> FF CA").is_err());
+        assert!(Replay::parse("> FF C\n< 90 00").is_err());
+        assert!(Replay::parse("> GG\n< 90 00").is_err());
+    }
+}
@@ -0,0 +1,156 @@
+use crate::card::transport::Transport;
+use anyhow::anyhow;
+
+// SELECT by name of the NDEF Tag Application for mapping version 2.0.
+const SELECT_APPLICATION: &[u8] = b"\x00\xA4\x04\x00\x07\xD2\x76\x00\x00\x85\x01\x01\x00";
+// The file identifier of the Capability Container.
+const CC_FILE: [u8; 2] = [0xE1, 0x03];
+// The length of the Capability Container up to the end of the NDEF File Control TLV.
+const CC_LENGTH: usize = 0x0F;
+// The major version of the supported mapping.
+const MAPPING_VERSION: u8 = 2;
+// The tag of the TLV in the Capability Container that describes the NDEF file.
+const NDEF_FILE_CONTROL_TLV: u8 = 0x04;
+// The access condition for files that can be read without security.
+const FREE_ACCESS: u8 = 0x00;
+// Maximum number of bytes to read with a short READ BINARY.
+const MAX_READ_BYTES: usize = 0xFF;
+
+/// Reads the NDEF message from the NDEF file of a Type 4 tag.
+/// Returns none when the file is empty.
+pub(super) fn read_message(transport: &mut impl Transport) -> anyhow::Result<Option<Vec<u8>>> {
+    transport.execute(SELECT_APPLICATION, "select the NDEF application")?;
+    select(transport, CC_FILE)?;
+
+    let cc = read_binary(transport, 0, CC_LENGTH, MAX_READ_BYTES)?;
+    let (file, max_read) = parse_capabilities(&cc)?;
+
+    select(transport, file)?;
+
+    let length = read_binary(transport, 0, 2, max_read)?;
+    let length = u16::from_be_bytes([length[0], length[1]]) as usize;
+    if length == 0 {
+        return Ok(None);
+    }
+
+    Ok(Some(read_binary(transport, 2, length, max_read)?))
+}
+
+/// The identifier of the NDEF file and the maximum number of bytes per read in the Capability Container.
+fn parse_capabilities(cc: &[u8]) -> anyhow::Result<([u8; 2], usize)> {
+    if cc.len() < CC_LENGTH {
+        return Err(anyhow!("The capability container is too short"));
+    }
+
+    // The mapping version and MLe are followed by MLc and the NDEF File Control TLV.
+    let (version, mle) = (cc[2], u16::from_be_bytes([cc[3], cc[4]]));
+    let (tag, length, file, read) = (cc[7], cc[8], [cc[9], cc[10]], cc[13]);
+
+    if version >> 4 != MAPPING_VERSION {
+        return Err(anyhow!("Unsupported mapping version {version:02X}"));
+    }
+    if tag != NDEF_FILE_CONTROL_TLV || length < 6 {
+        return Err(anyhow!("The capability container has no NDEF file"));
+    }
+    if read != FREE_ACCESS {
+        return Err(anyhow!("The NDEF file cannot be read"));
+    }
+
+    Ok((file, usize::from(mle).clamp(1, MAX_READ_BYTES)))
+}
+
+/// Selects the elementary file by its identifier.
+fn select(transport: &mut impl Transport, file: [u8; 2]) -> anyhow::Result<()> {
+    let [high, low] = file;
+
+    transport
+        .execute(
+            &[0x00, 0xA4, 0x00, 0x0C, 0x02, high, low],
+            "select the file",
+        )
+        .map(|_| ())
+}
+
+/// Reads the bytes at the offset of the selected file, at most `max_read` at a time.
+fn read_binary(
+    transport: &mut impl Transport,
+    offset: usize,
+    length: usize,
+    max_read: usize,
+) -> anyhow::Result<Vec<u8>> {
+    let mut data = Vec::with_capacity(length);
+
+    while data.len() < length {
+        let position = u16::try_from(offset + data.len())
+            .ok()
+            .filter(|position| *position <= 0x7FFF)
+            .ok_or_else(|| anyhow!("The offset is out of range for READ BINARY"))?;
+        let [high, low] = position.to_be_bytes();
+        let requested = (length - data.len()).min(max_read);
+
+        let chunk =
+            transport.execute(&[0x00, 0xB0, high, low, requested as u8], "read the file")?;
+        if chunk.is_empty() {
+            return Err(anyhow!("The read operation returned no bytes"));
+        }
+
+        data.extend_from_slice(&chunk[..chunk.len().min(requested)]);
+    }
+
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::SUCCESS;
+
+    const CC: &[u8] = b"\x00\x0F\x20\x00\x3B\x00\x34\x04\x06\xE1\x04\x00\xFF\x00\xFF";
+
+    #[test]
+    fn parses_capability_container() {
+        assert_eq!(parse_capabilities(CC).unwrap(), ([0xE1, 0x04], 0x3B));
+    }
+
+    #[test]
+    fn rejects_unreadable_capability_containers() {
+        assert!(parse_capabilities(&CC[..10]).is_err());
+
+        let mut version_3 = CC.to_vec();
+        version_3[2] = 0x30;
+        assert!(parse_capabilities(&version_3).is_err());
+
+        let mut locked = CC.to_vec();
+        locked[13] = 0x80;
+        assert!(parse_capabilities(&locked).is_err());
+    }
+
+    #[test]
+    fn reads_in_chunks_of_max_read() {
+        let mut offsets = Vec::new();
+        let mut transport = |command: &[u8]| -> anyhow::Result<Vec<u8>> {
+            offsets.push((u16::from_be_bytes([command[2], command[3]]), command[4]));
+            let mut response = vec![0xAB; command[4] as usize];
+            response.extend_from_slice(SUCCESS);
+            Ok(response)
+        };
+
+        let data = read_binary(&mut transport, 2, 600, MAX_READ_BYTES).unwrap();
+
+        assert_eq!(data.len(), 600);
+        assert_eq!(offsets, vec![(2, 0xFF), (257, 0xFF), (512, 90)]);
+    }
+
+    #[test]
+    fn empty_ndef_file_has_no_message() {
+        let mut transport = |command: &[u8]| -> anyhow::Result<Vec<u8>> {
+            match command {
+                [0x00, 0xB0, 0x00, 0x00, 0x0F] => Ok([CC, SUCCESS].concat()),
+                [0x00, 0xB0, 0x00, 0x00, 0x02] => Ok(b"\x00\x00\x90\x00".to_vec()),
+                _ => Ok(SUCCESS.to_vec()),
+            }
+        };
+
+        assert_eq!(read_message(&mut transport).unwrap(), None);
+    }
+}
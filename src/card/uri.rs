@@ -0,0 +1,138 @@
+use anyhow::anyhow;
+
+// The URI identifier codes from the NFC Forum URI Record Type Definition.
+// The index in the table is the code stored in the first byte of the record payload.
+const PREFIXES: [&str; 36] = [
+    "",
+    "http://www.",
+    "https://www.",
+    "http://",
+    "https://",
+    "tel:",
+    "mailto:",
+    "ftp://anonymous:anonymous@",
+    "ftp://ftp.",
+    "ftps://",
+    "sftp://",
+    "smb://",
+    "nfs://",
+    "ftp://",
+    "dav://",
+    "news:",
+    "telnet://",
+    "imap:",
+    "rtsp://",
+    "urn:",
+    "pop:",
+    "sip:",
+    "sips:",
+    "tftp:",
+    "btspp://",
+    "btl2cap://",
+    "btgoep://",
+    "tcpobex://",
+    "irdaobex://",
+    "file://",
+    "urn:epc:id:",
+    "urn:epc:tag:",
+    "urn:epc:pat:",
+    "urn:epc:raw:",
+    "urn:epc:",
+    "urn:nfc:",
+];
+
+/// The prefix abbreviated by the identifier code, if the code is not reserved for future use.
+pub fn prefix(code: u8) -> Option<&'static str> {
+    PREFIXES.get(code as usize).copied()
+}
+
+/// Splits the URI into the identifier code of its longest known prefix and the remainder.
+pub fn abbreviate(uri: &str) -> (u8, &str) {
+    PREFIXES
+        .iter()
+        .enumerate()
+        .skip(1)
+        .filter_map(|(code, prefix)| Some((code as u8, prefix.len(), uri.strip_prefix(prefix)?)))
+        .max_by_key(|(_, length, _)| *length)
+        .map(|(code, _, rest)| (code, rest))
+        .unwrap_or((0, uri))
+}
+
+/// Decodes a URI record payload made up of the identifier code followed by the URI field.
+pub fn decode(payload: &[u8]) -> anyhow::Result<String> {
+    let Some((code, rest)) = payload.split_first() else {
+        return Err(anyhow!("The URI record payload is empty"));
+    };
+    let prefix = prefix(*code).ok_or_else(|| anyhow!("Unknown URI identifier code {code:#04X}"))?;
+
+    let mut uri = String::with_capacity(prefix.len() + rest.len());
+    uri.push_str(prefix);
+    uri.push_str(std::str::from_utf8(rest)?);
+
+    Ok(uri)
+}
+
+/// Encodes the URI as a URI record payload using the longest matching identifier code.
+pub fn encode(uri: &str) -> Vec<u8> {
+    let (code, rest) = abbreviate(uri);
+
+    let mut payload = Vec::with_capacity(rest.len() + 1);
+    payload.push(code);
+    payload.extend_from_slice(rest.as_bytes());
+    payload
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_every_code() {
+        for code in 0..=0x23u8 {
+            let mut payload = vec![code];
+            payload.extend_from_slice(b"example");
+
+            let uri = decode(&payload).unwrap();
+            assert_eq!(uri, format!("{}example", PREFIXES[code as usize]));
+        }
+    }
+
+    #[test]
+    fn rejects_reserved_codes() {
+        assert!(decode(b"\x24example").is_err());
+        assert!(decode(b"\xFFexample").is_err());
+        assert!(decode(b"").is_err());
+    }
+
+    #[test]
+    fn abbreviates_longest_prefix() {
+        assert_eq!(abbreviate("https://www.example.com"), (0x02, "example.com"));
+        assert_eq!(abbreviate("http://example.com"), (0x03, "example.com"));
+        assert_eq!(abbreviate("ftp://ftp.example.com"), (0x08, "example.com"));
+        assert_eq!(abbreviate("urn:epc:id:sgtin:1"), (0x1E, "sgtin:1"));
+        assert_eq!(abbreviate("urn:nfc:sn:1"), (0x23, "sn:1"));
+        assert_eq!(abbreviate("file:///music/album"), (0x1D, "/music/album"));
+    }
+
+    #[test]
+    fn does_not_abbreviate_unknown_schemes() {
+        assert_eq!(abbreviate("spotify:album:1"), (0x00, "spotify:album:1"));
+    }
+
+    #[test]
+    fn round_trips_uris() {
+        let uris = [
+            "spotify:playlist:37i9dQZF1DXcBWIGoYBM5M",
+            "https://open.spotify.com/album/4aawyAB9vmqN3uQ7FjRGTy",
+            "http://www.example.com/path?query=1",
+            "file:///stories/bedtime",
+            "mailto:jukebox@example.com",
+            "urn:epc:raw:1234",
+            "tel:+15555555555",
+        ];
+
+        for uri in uris {
+            assert_eq!(decode(&encode(uri)).unwrap(), uri);
+        }
+    }
+}
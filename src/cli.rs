@@ -1,5 +1,9 @@
-use clap::Parser;
+use crate::card::Password;
+use crate::player::{Order, Removal, Source, parse_duration, parse_percent};
+use crate::zone::Zone;
+use clap::{Parser, Subcommand};
 use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Debug, Parser)]
 #[command(author, version, about, long_about)]
@@ -24,4 +28,85 @@ pub struct Arguments {
 
     #[arg(short, long, env = "JUKEBOX_LOCAL_MUSIC_PATH")]
     pub local_music_path: PathBuf,
+
+    /// File that maps card UIDs to URIs for cards without NDEF content.
+    #[arg(long, env = "JUKEBOX_CARD_REGISTRY")]
+    pub card_registry: Option<PathBuf>,
+
+    /// File that keeps the URIs queued to play after the current one across restarts.
+    /// Players of other zones keep theirs next to it, named after their device.
+    #[arg(long, env = "JUKEBOX_QUEUE_FILE")]
+    pub queue_file: Option<PathBuf>,
+
+    /// File that keeps where each URI stopped playing, so it resumes from there.
+    #[arg(long, env = "JUKEBOX_BOOKMARKS_FILE")]
+    pub bookmarks_file: Option<PathBuf>,
+
+    /// Sources that always play from the start instead of resuming: stream, file.
+    /// Single cards opt out with the `resume=off` query parameter, as in `file:///songs?resume=off`
+    /// or `spotify:album:1?resume=off`.
+    #[arg(long, env = "JUKEBOX_NO_RESUME", value_delimiter = ',')]
+    pub no_resume: Vec<Source>,
+
+    /// The order to play songs in: sequential, natural, shuffle, shuffle-by-album or weighted.
+    /// Single cards choose another with the `order` query parameter, as in
+    /// `file:///stories?order=natural`.
+    #[arg(long, env = "JUKEBOX_ORDER", default_value = "shuffle")]
+    pub order: Order,
+
+    /// The loudest the volume gets, in percent, whatever the cards or the web interface ask for.
+    /// Single cards start at a volume of their own with the `volume` query parameter, as in
+    /// `spotify:album:1?volume=30`.
+    #[arg(long, env = "JUKEBOX_MAX_VOLUME", default_value = "100", value_parser = parse_percent)]
+    pub max_volume: u8,
+
+    /// What to do when a card is removed: pause, ignore, resume or grace:<duration>.
+    #[arg(long, env = "JUKEBOX_CARD_REMOVAL", default_value = "pause")]
+    pub card_removal: Removal,
+
+    /// How long a card must be away before its removal counts, such as 500ms.
+    /// A card presented again sooner is RF flicker and is ignored, later it is a re-tap.
+    #[arg(long, env = "JUKEBOX_CARD_DEBOUNCE", default_value = "1s", value_parser = parse_duration)]
+    pub card_debounce: Duration,
+
+    /// Watch every reader whose name contains this text.
+    #[arg(long, env = "JUKEBOX_READER_NAME", default_value = "PICC")]
+    pub reader_name: String,
+
+    /// Route the readers whose name contains <reader> to the player for a Spotify <device>.
+    /// Readers without a zone share the player for the default device.
+    #[arg(long = "zone", env = "JUKEBOX_ZONES", value_delimiter = ',')]
+    pub zones: Vec<Zone>,
+
+    /// Password that protects finalized cards against writes, as 8 hexadecimal digits.
+    #[arg(long, env = "JUKEBOX_CARD_PASSWORD")]
+    pub card_password: Option<Password>,
+
+    /// Simulate a reader instead of using PC/SC, reading steps such as
+    /// `insert <uri>`, `remove` and `wait 5s` from the file, or from stdin when `-`.
+    #[arg(long, env = "JUKEBOX_SIMULATE")]
+    pub simulate: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Clone, Debug, Subcommand)]
+pub enum Command {
+    /// Wait for a card to be presented and program it with the given URI.
+    Write { uri: String },
+    /// Wait for a card to be presented and record the APDUs exchanged to read it to a fixture file.
+    Dump { path: PathBuf },
+    /// Wait for an NTAG21x card to be presented and protect it against being overwritten.
+    Finalize {
+        /// Make the card read-only, which cannot be undone.
+        #[arg(long)]
+        lock: bool,
+        /// Require the card password to write to the card.
+        #[arg(long)]
+        protect: bool,
+        /// Only show what would be written to the card.
+        #[arg(long)]
+        dry_run: bool,
+    },
 }
@@ -1,73 +1,173 @@
-use std::collections::VecDeque;
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Debug;
 use std::sync::{Arc, RwLock};
-use tracing_subscriber::fmt::MakeWriter;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::Layer;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
 
+// Number of records kept for clients that connect later.
+const CAPACITY: usize = 100;
+
+/// A log event with its structured fields, for the web UI to format and filter.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Record {
+    pub level: String,
+    pub target: String,
+    /// Milliseconds since the Unix epoch.
+    pub timestamp: u64,
+    /// The spans the event happened in, from the outermost, separated by `:`.
+    pub span: Option<String>,
+    pub message: String,
+    pub fields: BTreeMap<String, String>,
+}
+
+/// Keeps the latest log records and streams new ones to subscribers.
 #[derive(Clone)]
 pub struct Screen {
-    events: Arc<RwLock<VecDeque<String>>>,
+    records: Arc<RwLock<VecDeque<Record>>>,
+    live: broadcast::Sender<Record>,
 }
 
 impl Default for Screen {
     fn default() -> Self {
         Self {
-            events: Arc::new(RwLock::new(VecDeque::with_capacity(100))),
+            records: Arc::new(RwLock::new(VecDeque::with_capacity(CAPACITY))),
+            live: broadcast::channel(CAPACITY).0,
         }
     }
 }
 
-const DELIMITER: &str = "<br>";
-
 impl Screen {
-    pub fn read(&self) -> String {
-        match self.events.read() {
-            Ok(guard) => {
-                let bytes: usize = guard.iter().map(String::len).sum();
-                let mut result = String::with_capacity(bytes + (DELIMITER.len() * guard.len()));
-
-                for s in guard.iter() {
-                    result.push_str(s);
-                    result.push_str(DELIMITER);
-                }
-
-                result
-            }
-            Err(_) => String::new(),
-        }
+    /// The latest records, and a receiver for every record after them.
+    pub fn subscribe(&self) -> (Vec<Record>, broadcast::Receiver<Record>) {
+        // Records are pushed and sent under the write lock, so none are missed or repeated.
+        let guard = self.records.read().unwrap_or_else(|e| e.into_inner());
+
+        (guard.iter().cloned().collect(), self.live.subscribe())
     }
-}
 
-impl std::io::Write for Screen {
-    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        let text = std::str::from_utf8(buf)
-            .map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid utf-8"))?;
-        let mut guard = self.events.write().unwrap_or_else(|e| {
-            let mut guard = e.into_inner();
-            guard.clear();
-            guard
-        });
+    fn push(&self, record: Record) {
+        let mut guard = self.records.write().unwrap_or_else(|e| e.into_inner());
 
         // Prevent the ring buffer from growing.
-        if guard.len() == guard.capacity() {
+        if guard.len() == CAPACITY {
             guard.pop_front();
         }
 
-        let html = ansi_to_html::convert(text).map_err(|_| {
-            std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid ansi text")
-        })?;
+        guard.push_back(record.clone());
+        let _ = self.live.send(record);
+    }
+}
+
+impl<S> Layer<S> for Screen
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_event(&self, event: &Event<'_>, context: Context<'_, S>) {
+        let metadata = event.metadata();
+        let mut visitor = Fields::default();
+        event.record(&mut visitor);
+
+        let span = context.event_scope(event).map(|scope| {
+            scope
+                .from_root()
+                .map(|span| span.name())
+                .collect::<Vec<_>>()
+                .join(":")
+        });
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_millis() as u64);
+
+        self.push(Record {
+            level: metadata.level().to_string(),
+            target: metadata.target().to_string(),
+            timestamp,
+            span,
+            message: visitor.message,
+            fields: visitor.fields,
+        });
+    }
+}
+
+/// Collects the message and the other fields of an event as text.
+#[derive(Default)]
+struct Fields {
+    message: String,
+    fields: BTreeMap<String, String>,
+}
 
-        guard.push_back(html);
-        Ok(buf.len())
+impl Fields {
+    fn insert(&mut self, field: &Field, value: String) {
+        match field.name() {
+            "message" => self.message = value,
+            name => {
+                self.fields.insert(name.to_string(), value);
+            }
+        }
     }
+}
 
-    fn flush(&mut self) -> std::io::Result<()> {
-        Ok(())
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.insert(field, format!("{value:?}"));
     }
 }
 
-impl<'a> MakeWriter<'a> for Screen {
-    type Writer = Self;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn records_structured_fields() {
+        let screen = Screen::default();
+        let subscriber = tracing_subscriber::registry().with(screen.clone());
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("player");
+            let _entered = span.enter();
+            tracing::warn!(uri = "spotify:album:1", count = 2, "Failed to play");
+        });
+
+        let (records, _) = screen.subscribe();
+        let record = &records[0];
+
+        assert_eq!(record.level, "WARN");
+        assert_eq!(record.target, module_path!());
+        assert_eq!(record.span.as_deref(), Some("player"));
+        assert_eq!(record.message, "Failed to play");
+        assert_eq!(record.fields["uri"], "spotify:album:1");
+        assert_eq!(record.fields["count"], "2");
+        assert!(record.timestamp > 0);
+    }
+
+    #[test]
+    fn keeps_latest_records_and_streams_new_ones() {
+        let screen = Screen::default();
+        let subscriber = tracing_subscriber::registry().with(screen.clone());
 
-    fn make_writer(&'a self) -> Self::Writer {
-        self.clone()
+        tracing::subscriber::with_default(subscriber, || {
+            for index in 0..CAPACITY + 5 {
+                tracing::info!(index, "Logged");
+            }
+
+            let (records, mut live) = screen.subscribe();
+            assert_eq!(records.len(), CAPACITY);
+            assert_eq!(records[0].fields["index"], "5");
+            assert_eq!(records[0].span, None);
+
+            tracing::info!("Streamed");
+            assert_eq!(live.try_recv().unwrap().message, "Streamed");
+        });
     }
 }
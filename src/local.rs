@@ -3,20 +3,24 @@ use std::fs::File;
 use std::io::BufReader;
 use std::path::{Component, Path, PathBuf};
 use std::time::Duration;
-use rand::prelude::SliceRandom;
 use walkdir::WalkDir;
+use crate::player::{Bookmark, Playback, Song, Track};
 
 pub struct Player {
     base_path: PathBuf,
-    audio: Option<(OutputStream, Sink)>
+    audio: Option<(OutputStream, Sink)>,
+    volume: f32,
+    // The songs appended to the sink, in the order they play.
+    songs: Vec<Song>,
 }
 
 impl Player {
     pub fn new(base_path: PathBuf) -> Self {
-        Self { base_path, audio: None }
+        Self { base_path, audio: None, volume: 1.0, songs: Vec::new() }
     }
 
-    pub async fn play(&mut self, uri: String) -> anyhow::Result<Vec<Duration>> {
+    /// The files under the path of the URI, in the order of their paths.
+    pub async fn songs(&mut self, uri: String) -> anyhow::Result<Vec<Song>> {
         // Strip the scheme and root path from the URI.
         // This forces the URI to be a relative path.
         let Some(file_path) = uri.strip_prefix("file:///") else {
@@ -29,41 +33,113 @@ impl Player {
         }
 
         let mut songs = Vec::new();
-        for entry in WalkDir::new(&joined_path) {
+        for entry in WalkDir::new(&joined_path).sort_by_file_name() {
             let dir_entry = entry?;
             if dir_entry.file_type().is_file() {
-                songs.push(dir_entry.into_path());
+                let path = dir_entry.into_path();
+                songs.push(Song {
+                    id: path.to_string_lossy().into_owned(),
+                    // Only known once the file is decoded.
+                    duration: Duration::ZERO,
+                    album: path
+                        .parent()
+                        .map(|folder| folder.to_string_lossy().into_owned())
+                        .unwrap_or_default(),
+                });
             }
         }
 
-        tracing::debug!(?songs, "Playing songs from {}", joined_path.display());
+        tracing::debug!(?songs, "Found songs in {}", joined_path.display());
 
+        Ok(songs)
+    }
+
+    /// Plays the songs in order, from the position in the first song.
+    pub async fn play(
+        &mut self,
+        mut songs: Vec<Song>,
+        position: Duration,
+    ) -> anyhow::Result<Vec<Duration>> {
         if songs.is_empty() {
-            return Ok(Vec::new());
+            return Err(anyhow::anyhow!("No songs to play"));
         }
 
-        // Shuffle the songs to get a different order each time.
-        songs.shuffle(&mut rand::rng());
+        for song in &songs {
+            let path = normalize_path(&song.id);
+            if !path.starts_with(&self.base_path) || !path.is_file() {
+                return Err(anyhow::anyhow!("Missing file: {}", path.display()));
+            }
+        }
 
         // Get an output stream handle to the default physical sound device.
         // Note that the playback stops when the stream_handle is dropped.
         let stream_handle =
             OutputStreamBuilder::open_default_stream()?;
+        let sink = self.decode(&stream_handle, &mut songs, position, false)?;
+
+        // The sound plays in a separate audio thread,
+        // so we need to keep the main thread alive while it's playing.
+        self.audio = Some((stream_handle, sink));
+        self.songs = songs;
+
+        Ok(self.songs.iter().map(|song| song.duration).collect())
+    }
+
+    /// Decodes the songs into a new sink, noting their durations, and seeks to the position.
+    fn decode(
+        &self,
+        stream_handle: &OutputStream,
+        songs: &mut [Song],
+        position: Duration,
+        paused: bool,
+    ) -> anyhow::Result<Sink> {
         let sink = Sink::connect_new(stream_handle.mixer());
+        sink.set_volume(self.volume);
+        if paused {
+            sink.pause();
+        }
 
-        let mut durations = Vec::with_capacity(songs.len());
-        for path in songs {
-            let file = BufReader::new(File::open(&path)?);
+        for song in songs.iter_mut() {
+            let file = BufReader::new(File::open(&song.id)?);
             let source = Decoder::try_from(file)?;
-            durations.push(source.total_duration().unwrap_or(Duration::ZERO));
+            song.duration = source.total_duration().unwrap_or(Duration::ZERO);
             sink.append(source);
         }
 
-        // The sound plays in a separate audio thread,
-        // so we need to keep the main thread alive while it's playing.
-        self.audio = Some((stream_handle, sink));
+        if !position.is_zero()
+            && let Err(e) = sink.try_seek(position)
+        {
+            tracing::warn!(%e, ?position, "Failed to seek, playing the song from the start");
+        }
+
+        Ok(sink)
+    }
+
+    /// Plays the playlist again from the song at the index, replacing the sink.
+    /// The new sink stays paused when the old one was.
+    fn replay(&mut self, index: usize) -> anyhow::Result<()> {
+        let Some((stream_handle, sink)) = self.audio.as_ref() else {
+            return Ok(());
+        };
+
+        let mut songs = self.songs.split_off(index);
+        let decoded = self.decode(stream_handle, &mut songs, Duration::ZERO, sink.is_paused());
+        self.songs.append(&mut songs);
+
+        // Dropping the old sink stops the songs left in it.
+        if let Some((_, sink)) = self.audio.as_mut() {
+            *sink = decoded?;
+        }
 
-        Ok(durations)
+        Ok(())
+    }
+
+    /// The index of the song playing in the playlist, or none when every song played.
+    fn current(&self) -> Option<usize> {
+        let (_, sink) = self.audio.as_ref()?;
+        let remaining = sink.len().min(self.songs.len());
+
+        (remaining > 0).then(|| self.songs.len() - remaining)
     }
 
     pub async fn skip(&mut self) -> anyhow::Result<bool> {
@@ -76,6 +152,46 @@ impl Player {
         Ok(false)
     }
 
+    /// Plays the song before the current one, or the last song once every song played.
+    pub async fn previous(&mut self) -> anyhow::Result<()> {
+        let index = match self.current() {
+            Some(index) => index.saturating_sub(1),
+            None => self.songs.len().saturating_sub(1),
+        };
+
+        self.replay(index)
+    }
+
+    /// Plays the current song from its start.
+    pub async fn restart(&mut self) -> anyhow::Result<()> {
+        let Some(index) = self.current() else {
+            return Ok(());
+        };
+
+        if let Some((_, sink)) = self.audio.as_mut() {
+            match sink.try_seek(Duration::ZERO) {
+                Ok(()) => {
+                    sink.play();
+                    return Ok(());
+                }
+                Err(e) => tracing::debug!(%e, "Failed to seek, decoding the song again"),
+            }
+        }
+
+        self.replay(index)?;
+        self.resume().await
+    }
+
+    /// Plays the current song from the position.
+    pub async fn seek(&mut self, position: Duration) -> anyhow::Result<()> {
+        if let Some((_, sink)) = self.audio.as_mut() {
+            sink.try_seek(position)
+                .map_err(|e| anyhow::anyhow!("Failed to seek to {position:?}: {e}"))?;
+        }
+
+        Ok(())
+    }
+
     pub async fn pause(&mut self) -> anyhow::Result<()> {
         if let Some((_, sink)) = self.audio.as_mut() {
             sink.pause();
@@ -83,6 +199,84 @@ impl Player {
 
         Ok(())
     }
+
+    pub async fn resume(&mut self) -> anyhow::Result<()> {
+        if let Some((_, sink)) = self.audio.as_mut() {
+            sink.play();
+        }
+
+        Ok(())
+    }
+
+    pub async fn stop(&mut self) -> anyhow::Result<()> {
+        // Dropping the sink stops playback and clears the remaining songs.
+        self.audio = None;
+        self.songs.clear();
+
+        Ok(())
+    }
+
+    /// The volume in percent, kept for the songs played next.
+    pub async fn volume(&mut self) -> anyhow::Result<u8> {
+        Ok((self.volume * 100.0).round() as u8)
+    }
+
+    pub async fn set_volume(&mut self, percent: u8) -> anyhow::Result<()> {
+        self.volume = f32::from(percent) / 100.0;
+
+        if let Some((_, sink)) = self.audio.as_mut() {
+            sink.set_volume(self.volume);
+        }
+
+        Ok(())
+    }
+
+    /// The song playing and the songs after it, or none when every song played.
+    pub fn bookmark(&self) -> Option<Bookmark> {
+        let (_, sink) = self.audio.as_ref()?;
+        let remaining = sink.len().min(self.songs.len());
+        if remaining == 0 {
+            return None;
+        }
+
+        Some(Bookmark {
+            songs: self.songs[self.songs.len() - remaining..].to_vec(),
+            position: sink.get_pos(),
+        })
+    }
+
+    /// The song playing, titled by its file name, and the number of songs after it.
+    pub async fn playback(&mut self) -> anyhow::Result<Playback> {
+        let Some((_, sink)) = self.audio.as_ref() else {
+            return Ok(Playback::default());
+        };
+
+        // The sink drops songs once they finish, so the songs left in it end the playlist.
+        let remaining = sink.len().min(self.songs.len());
+        let song = self.songs[self.songs.len() - remaining..].first();
+        let track = song.map(|song| {
+            let path = Path::new(&song.id);
+            Track {
+                title: path
+                    .file_stem()
+                    .unwrap_or(path.as_os_str())
+                    .to_string_lossy()
+                    .into_owned(),
+                duration: song.duration,
+                ..Track::default()
+            }
+        });
+
+        Ok(Playback {
+            track,
+            song: song.map(|song| song.id.clone()),
+            volume: Some((self.volume * 100.0).round() as u8),
+            position: sink.get_pos(),
+            paused: sink.is_paused(),
+            queue: remaining.saturating_sub(1),
+            ended: sink.empty(),
+        })
+    }
 }
 
 // From https://github.com/rust-lang/cargo/blob/fede83ccf973457de319ba6fa0e36ead454d2e20/src/cargo/util/paths.rs#L61
@@ -115,8 +309,16 @@ pub fn normalize_path(path: impl AsRef<Path>) -> PathBuf {
 
 #[cfg(test)]
 mod tests {
-    use super::normalize_path;
+    use super::{Player, normalize_path};
     use std::path::PathBuf;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn refuses_to_play_without_songs() {
+        let mut player = Player::new(PathBuf::from("music"));
+
+        assert!(player.play(Vec::new(), Duration::ZERO).await.is_err());
+    }
 
     #[test]
     fn normalize_path_removes_current_dir() {
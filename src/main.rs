@@ -7,14 +7,29 @@ mod spotify;
 mod token;
 mod web;
 mod progress;
+mod store;
+mod zone;
 
-use crate::card::Reader;
-use crate::cli::Arguments;
+use crate::card::feedback::Feedback;
+use crate::card::{
+    Cards, Debounce, Finalize, Password, Reader, Registry, Simulated, Source, feedback,
+};
+use crate::cli::{Arguments, Command};
 use crate::console::Screen;
+use crate::player::{Bookmarks, PlayerState, Queue};
+use crate::zone::{Route, Zones};
 use clap::Parser;
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::CStr;
 use std::io;
-use tokio::sync::watch::Sender;
+use std::path::Path;
+use std::time::{Duration, Instant};
 use tracing_log::LogTracer;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+
+// Time to wait before reconnecting to the card readers after an error.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
 
 fn main() {
     let arguments = cli::Arguments::parse();
@@ -22,8 +37,38 @@ fn main() {
 
     tracing::debug!(?arguments, "starting jukebox server");
 
-    if let Err(e) = run(arguments, screen) {
-        tracing::error!(%e, "Unable to run the jukebox");
+    match arguments.command.clone() {
+        Some(Command::Write { uri }) => {
+            if let Err(e) = write_card(uri, &arguments.reader_name, arguments.card_password) {
+                tracing::error!(%e, "Unable to write the card");
+            }
+        }
+        Some(Command::Dump { path }) => {
+            if let Err(e) = dump_card(&path, &arguments.reader_name) {
+                tracing::error!(%e, "Unable to dump the card");
+            }
+        }
+        Some(Command::Finalize {
+            lock,
+            protect,
+            dry_run,
+        }) => {
+            let finalize = Finalize {
+                lock,
+                protect,
+                dry_run,
+            };
+
+            let password = arguments.card_password;
+            if let Err(e) = finalize_card(finalize, &arguments.reader_name, password) {
+                tracing::error!(%e, "Unable to finalize the card");
+            }
+        }
+        None => {
+            if let Err(e) = run(arguments, screen) {
+                tracing::error!(%e, "Unable to run the jukebox");
+            }
+        }
     }
 }
 
@@ -39,16 +84,16 @@ fn set_log_level(arguments: &Arguments) -> anyhow::Result<console::Screen> {
     };
 
     let screen = Screen::default();
-    let subscriber = tracing_subscriber::FmtSubscriber::builder()
-        .with_max_level(level)
-        .with_file(true)
-        .with_line_number(true)
-        .with_thread_ids(true)
-        .with_writer(tracing_subscriber::fmt::writer::Tee::new(
-            io::stderr,
-            screen.clone(),
-        ))
-        .finish();
+    let subscriber = tracing_subscriber::registry()
+        .with(LevelFilter::from_level(level))
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_file(true)
+                .with_line_number(true)
+                .with_thread_ids(true)
+                .with_writer(io::stderr),
+        )
+        .with(screen.clone());
 
     tracing::subscriber::set_global_default(subscriber)?;
 
@@ -61,28 +106,103 @@ fn run(arguments: Arguments, screen: Screen) -> anyhow::Result<()> {
         .enable_time()
         .build()?;
     let result: anyhow::Result<()> = runtime.block_on(async {
-        let (sender, receiver) = tokio::sync::watch::channel(None);
+        let (sender, receiver) = player::channel();
+        let (state, state_receiver) = tokio::sync::watch::channel(PlayerState::default());
+        let cards = Cards::new(Registry::load(arguments.card_registry)?);
+        let bookmarks = Bookmarks::load(arguments.bookmarks_file.clone())?;
 
         let mut group = tokio::task::JoinSet::new();
         let oauth = token::Client::new(arguments.client_id, arguments.token_cache);
         let client = spotify::Client::new(oauth.clone(), arguments.market);
         let stream_player = spotify::Player::new(client.clone(), arguments.device);
-        let file_player = local::Player::new(arguments.local_music_path);
+        let file_player = local::Player::new(arguments.local_music_path.clone());
 
         // Construct a local task set that can run `!Send` futures.
         let local = tokio::task::LocalSet::new();
 
+        let (signals, signal_receiver) = std::sync::mpsc::channel();
+        let feedback = Feedback::new(signals.clone());
+
+        // Readers in a zone drive the player for its device, and zones with the same device share it.
+        let mut zones = Zones::new(Route {
+            sender: sender.clone(),
+            feedback: feedback.clone(),
+        });
+        let mut devices = HashMap::new();
+        let mut zone_states = BTreeMap::new();
+        for zone in arguments.zones {
+            if !devices.contains_key(&zone.device) {
+                let (zone_sender, zone_receiver) = player::channel();
+                let (zone_state, zone_state_receiver) =
+                    tokio::sync::watch::channel(PlayerState::default());
+                let zone_feedback = Feedback::new(signals.clone());
+                let stream_player = spotify::Player::new(client.clone(), Some(zone.device.clone()));
+                let file_player = local::Player::new(arguments.local_music_path.clone());
+
+                // Each zone keeps its own queue next to the one of the default player.
+                let queue_file = arguments.queue_file.as_deref().map(|path| zone.queue_file(path));
+                let zone_player =
+                    player::Player::new(stream_player, file_player, arguments.card_removal)
+                        .with_queue(Queue::load(queue_file)?)
+                        .with_bookmarks(bookmarks.clone(), arguments.no_resume.clone())
+                        .with_order(arguments.order)
+                        .with_max_volume(arguments.max_volume);
+
+                group.spawn_local_on(
+                    player::run(
+                        zone_receiver,
+                        zone_player,
+                        zone_feedback.clone(),
+                        zone_state,
+                    ),
+                    &local,
+                );
+
+                let route = Route {
+                    sender: zone_sender,
+                    feedback: zone_feedback,
+                };
+                devices.insert(zone.device.clone(), route);
+                zone_states.insert(zone.device.clone(), zone_state_receiver);
+            }
+
+            zones.insert(zone.reader, devices[&zone.device].clone());
+        }
+
         group.spawn(web::run(
             sender.clone(),
-            receiver.clone(),
+            web::States {
+                default: state_receiver,
+                zones: zone_states,
+            },
+            cards.clone(),
             oauth,
             arguments.address,
             screen,
             client.clone(),
         ));
 
-        group.spawn_local_on(player::run(receiver, stream_player, file_player), &local);
-        group.spawn_blocking(|| read_loop(sender));
+        let default_player = player::Player::new(stream_player, file_player, arguments.card_removal)
+            .with_queue(Queue::load(arguments.queue_file.clone())?)
+            .with_bookmarks(bookmarks, arguments.no_resume.clone())
+            .with_order(arguments.order)
+            .with_max_volume(arguments.max_volume);
+        group.spawn_local_on(player::run(receiver, default_player, feedback, state), &local);
+        group.spawn_blocking(move || feedback::run(signal_receiver));
+        group.spawn_blocking(move || {
+            let mut debounce = Debounce::new(arguments.card_debounce);
+
+            match arguments.simulate {
+                Some(script) => simulate(&zones, &cards, &script, &mut debounce),
+                None => read_loop(
+                    zones,
+                    cards,
+                    &arguments.reader_name,
+                    arguments.card_password,
+                    &mut debounce,
+                ),
+            }
+        });
 
         while let Some(join_result) = local.run_until(group.join_next()).await {
             join_result??
@@ -98,23 +218,213 @@ fn run(arguments: Arguments, screen: Screen) -> anyhow::Result<()> {
     Ok(())
 }
 
-fn read_loop(sender: Sender<Option<String>>) -> anyhow::Result<()> {
+fn write_card(uri: String, pattern: &str, password: Option<Password>) -> anyhow::Result<()> {
     let ctx = pcsc::Context::establish(pcsc::Scope::User)?;
-    let mut reader = Reader::try_from(ctx)?;
+    let mut reader = Reader::matching(ctx, pattern)?.with_password(password);
 
-    tracing::debug!("Waiting for a card to be inserted");
+    tracing::info!(%uri, "Waiting for a card to be inserted");
 
     loop {
-        reader.wait(None)?;
-        match reader.read() {
-            Ok(card) => {
-                tracing::debug!(?card, "Read a card");
-                sender.send(card)?;
+        for name in reader.wait(None)? {
+            if reader.write(&name, &uri)? {
+                tracing::info!(%uri, ?name, "Wrote the URI to the card");
+                return Ok(());
             }
-            Err(e) => {
-                tracing::warn!(%e, "Failed to read the URI from the card");
-                sender.send(None)?;
+        }
+    }
+}
+
+fn finalize_card(
+    finalize: Finalize,
+    pattern: &str,
+    password: Option<Password>,
+) -> anyhow::Result<()> {
+    if !finalize.lock && !finalize.protect {
+        return Err(anyhow::anyhow!("Nothing to do, pass --lock and/or --protect"));
+    }
+
+    let ctx = pcsc::Context::establish(pcsc::Scope::User)?;
+    let mut reader = Reader::matching(ctx, pattern)?.with_password(password);
+
+    tracing::info!(?finalize, "Waiting for a card to be inserted");
+
+    loop {
+        for name in reader.wait(None)? {
+            if let Some(plan) = reader.finalize(&name, finalize)? {
+                if finalize.dry_run {
+                    tracing::info!(%plan, ?name, "Would write");
+                } else {
+                    tracing::info!(%plan, ?name, "Finalized the card");
+                }
+
+                return Ok(());
             }
         }
     }
 }
+
+fn dump_card(path: &Path, pattern: &str) -> anyhow::Result<()> {
+    let ctx = pcsc::Context::establish(pcsc::Scope::User)?;
+    let mut reader = Reader::matching(ctx, pattern)?;
+
+    tracing::info!(?path, "Waiting for a card to be inserted");
+
+    loop {
+        for name in reader.wait(None)? {
+            if let Some((tag, fixture)) = reader.dump(&name)? {
+                std::fs::write(path, fixture)?;
+                tracing::info!(?tag, ?path, "Recorded the card");
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn read_loop(
+    zones: Zones,
+    cards: Cards,
+    pattern: &str,
+    password: Option<Password>,
+    debounce: &mut Debounce,
+) -> anyhow::Result<()> {
+    loop {
+        let Err(e) = watch_readers(&zones, &cards, pattern, password.clone(), debounce) else {
+            return Ok(());
+        };
+
+        // Only smart card errors are recoverable, everything else stops the jukebox.
+        if e.downcast_ref::<pcsc::Error>().is_none() {
+            return Err(e);
+        }
+
+        tracing::warn!(%e, "Lost the connection to the card readers, reconnecting");
+        cards.set_readers(Vec::new());
+
+        // Without the readers nobody can tell the cards are still there, so stop playing them.
+        for name in debounce.disconnect() {
+            remove(&zones, &cards, &name)?;
+        }
+
+        std::thread::sleep(RECONNECT_DELAY);
+    }
+}
+
+fn watch_readers(
+    zones: &Zones,
+    cards: &Cards,
+    pattern: &str,
+    password: Option<Password>,
+    debounce: &mut Debounce,
+) -> anyhow::Result<()> {
+    let ctx = pcsc::Context::establish(pcsc::Scope::User)?;
+    let mut reader = Reader::matching(ctx, pattern)?.with_password(password);
+
+    watch(&mut reader, zones, cards, debounce)
+}
+
+/// Presents the cards from the script, or stdin when `-`, to the players.
+fn simulate(
+    zones: &Zones,
+    cards: &Cards,
+    script: &Path,
+    debounce: &mut Debounce,
+) -> anyhow::Result<()> {
+    tracing::info!(?script, "Simulating the card reader");
+
+    let mut source = if script == Path::new("-") {
+        Simulated::new(io::BufReader::new(io::stdin()))
+    } else {
+        Simulated::new(io::BufReader::new(std::fs::File::open(script)?))
+    };
+
+    watch(&mut source, zones, cards, debounce)
+}
+
+/// Sends the cards presented to the source to the players until it has no more cards.
+/// Removals are held back until they outlast the debounce, so flicker keeps playing.
+fn watch(
+    source: &mut impl Source,
+    zones: &Zones,
+    cards: &Cards,
+    debounce: &mut Debounce,
+) -> anyhow::Result<()> {
+    cards.set_readers(source.names());
+    tracing::debug!("Waiting for a card to be inserted");
+
+    loop {
+        let timeout = debounce
+            .deadline()
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()));
+        let Some(toggled) = source.wait(timeout)? else {
+            tracing::info!("No more cards to present");
+            return Ok(());
+        };
+
+        cards.set_readers(source.names());
+        let now = Instant::now();
+
+        for name in toggled {
+            let route = zones.route(&name.to_string_lossy());
+
+            match source.read(&name) {
+                Ok(Some(tag)) => {
+                    tracing::debug!(?tag, "Read a card");
+
+                    let returned = debounce.returned(&tag);
+                    if !debounce.inserted(&tag, now) {
+                        tracing::debug!(?name, "Ignoring the card presented again after flicker");
+                        continue;
+                    }
+
+                    // Play the bound URI or the first URI on the card that the player can handle.
+                    route.feedback.presented(&tag.reader);
+                    let uri = cards.resolve(source, &tag, player::supports);
+
+                    cards.publish(card::Event::Inserted {
+                        reader: name.to_string_lossy().into_owned(),
+                        uid: tag.uid.clone(),
+                        uri: uri.clone(),
+                    });
+
+                    // The card stayed while reconnecting, so it carries on instead of a re-tap.
+                    let command = match returned {
+                        true => player::Command::Resume,
+                        false => player::Command::Play(uri),
+                    };
+                    route.sender.send(command)?;
+                }
+                Ok(None) => {
+                    if debounce.removed(&name, now) {
+                        remove(zones, cards, &name)?;
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!(%e, ?name, "Failed to read the URI from the card");
+
+                    if debounce.removed(&name, now) {
+                        remove(zones, cards, &name)?;
+                    }
+                }
+            }
+        }
+
+        for name in debounce.expired(Instant::now()) {
+            remove(zones, cards, &name)?;
+        }
+
+        // The readers report their cards in the first change after reconnecting.
+        debounce.settle();
+    }
+}
+
+/// Tells the player of the zone and the subscribers that the card was removed from the reader.
+fn remove(zones: &Zones, cards: &Cards, name: &CStr) -> anyhow::Result<()> {
+    let reader = name.to_string_lossy().into_owned();
+
+    if let Some(uri) = cards.presented(&reader) {
+        zones.route(&reader).sender.send(player::Command::Remove(uri))?;
+    }
+    cards.publish(card::Event::Removed { reader });
+
+    Ok(())
+}
@@ -1,35 +1,170 @@
+mod action;
+mod bookmark;
+mod command;
+mod options;
+mod order;
+mod queue;
+mod removal;
+mod state;
+mod volume;
+
 use std::time::Duration;
-use tokio::sync::watch::Receiver;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::sync::watch;
+use tokio::time::Instant;
 use url::Url;
+use crate::card::feedback::{Feedback, Signal};
 use crate::{local, spotify};
+use crate::player::action::Action;
+use crate::player::command::Request;
+use crate::player::options::Options;
+use crate::player::order::History;
 use crate::progress::SongTracker;
 
+pub use crate::player::bookmark::{Bookmark, Bookmarks, Song};
+pub use crate::player::command::{Command, Commands, channel};
+pub use crate::player::order::Order;
+pub use crate::player::queue::Queue;
+pub use crate::player::removal::Removal;
+pub use crate::player::state::{Playback, PlayerState, Source, Track};
+pub use crate::player::volume::Volume;
+pub(crate) use crate::player::action::parse_duration;
+pub(crate) use crate::player::volume::parse_percent;
+
+// Percentage points to change the volume by for each volume action.
+const VOLUME_STEP: i32 = 10;
+// Time between checks of what the backend is playing, to notice tracks ending.
+const STATE_INTERVAL: Duration = Duration::from_secs(5);
+// Time between checks while URIs are queued, so the next one starts soon after the current one.
+const QUEUED_STATE_INTERVAL: Duration = Duration::from_secs(1);
+
 pub struct Player {
     stream: spotify::Player,
     file: local::Player,
     last: Option<String>,
     tracker: SongTracker,
+    // The order of URIs without one on the card, and the one the shuffle toggle switches to.
+    order: Order,
+    toggled: Order,
+    // The songs played recently, for the weighted shuffle.
+    history: History,
+    sleep: Option<Instant>,
+    removal: Removal,
+    grace: Option<Instant>,
+    queue: Queue,
+    // Whether presented cards are queued instead of replacing the playback.
+    enqueue: bool,
+    bookmarks: Bookmarks,
+    // The sources that always play from the start.
+    no_resume: Vec<Source>,
+    max_volume: u8,
+    // The volume from before muting, while muted.
+    muted: Option<u8>,
 }
 
 impl Player {
-    fn new(stream: spotify::Player, file: local::Player) -> Self {
+    pub fn new(stream: spotify::Player, file: local::Player, removal: Removal) -> Self {
         Self {
             stream,
             file,
             last: None,
-            tracker: SongTracker::default()
+            tracker: SongTracker::default(),
+            order: Order::Shuffle,
+            toggled: Order::Natural,
+            history: History::default(),
+            sleep: None,
+            removal,
+            grace: None,
+            queue: Queue::default(),
+            enqueue: false,
+            bookmarks: Bookmarks::default(),
+            no_resume: Vec::new(),
+            max_volume: volume::MAX_VOLUME,
+            muted: None,
+        }
+    }
+
+    pub fn with_queue(mut self, queue: Queue) -> Self {
+        self.queue = queue;
+        self
+    }
+
+    /// Keeps the volume at or below the maximum, in percent.
+    pub fn with_max_volume(mut self, max_volume: u8) -> Self {
+        self.max_volume = max_volume;
+        self
+    }
+
+    /// Plays the songs of URIs without an order on the card in the order.
+    pub fn with_order(mut self, order: Order) -> Self {
+        self.order = order;
+        self.toggled = match order {
+            Order::Sequential | Order::Natural => Order::Shuffle,
+            Order::Shuffle | Order::ShuffleByAlbum | Order::Weighted => Order::Natural,
+        };
+        self
+    }
+
+    /// Resumes URIs from the bookmarks, except those from the sources that play from the start.
+    pub fn with_bookmarks(mut self, bookmarks: Bookmarks, no_resume: Vec<Source>) -> Self {
+        self.bookmarks = bookmarks;
+        self.no_resume = no_resume;
+        self
+    }
+
+    /// Carries out the command on the current playback.
+    pub async fn execute(&mut self, command: Command) -> anyhow::Result<()> {
+        match command {
+            Command::Play(input) => self.play(input).await,
+            // Nothing to wait for, so play it right away.
+            Command::Enqueue(input) if self.last.is_none() => self.play(input).await,
+            command => self.control(command).await,
         }
     }
 
     pub async fn play(&mut self, input: String) -> anyhow::Result<()> {
-        if self.last.as_ref() == Some(&input) && self.tracker.has_next() {
-            if self.skip(&input).await? {
-                self.tracker.start();
-                return Ok(());
+        let uri = Url::parse(&input)?;
+
+        // Control cards act on the current playback instead of replacing it.
+        if uri.scheme() == action::SCHEME {
+            return self.control(Action::try_from(&uri)?.into()).await;
+        }
+
+        let reinserted = self.last.as_ref() == Some(&input);
+
+        if self.enqueue && self.last.is_some() && !reinserted {
+            self.control(Command::Enqueue(input)).await?;
+
+            // Queueing a card carries on the playback the removal of the last card paused.
+            if self.tracker.is_paused() {
+                return self.resume().await;
+            }
+            self.grace = None;
+            return Ok(());
+        }
+
+        let grace = self.grace.take();
+
+        if reinserted {
+            match self.removal {
+                Removal::Resume => return self.resume().await,
+                // The song never stopped, so there is nothing to do.
+                Removal::Grace(_) if grace.is_some() => return Ok(()),
+                _ => {}
             }
         }
 
-       let songs = self.play_uri(input.clone()).await?;
+        if reinserted
+            && self.tracker.has_next()
+            && self.skip(&input).await?
+        {
+            self.tracker.start();
+            return Ok(());
+        }
+
+        // Remember where the URI it replaces stopped.
+        self.remember().await;
+        let songs = self.play_uri(input.clone()).await?;
 
         self.last = Some(input);
         self.tracker.reset(songs);
@@ -38,39 +173,330 @@ impl Player {
 
     async fn skip(&mut self, input: &str) -> anyhow::Result<bool> {
         tracing::debug!(%input, "Playing next song");
-        let uri = Url::parse(input)?;
-        match uri.scheme() {
-            "https" if uri.host_str() == Some("open.spotify.com") =>self.stream.skip().await,
-            "spotify" => self.stream.skip().await,
-            "file" => self.file.skip().await,
-            _ => anyhow::bail!("Unknown scheme: {}", uri.scheme()),
+        match Source::try_from(input)? {
+            Source::Stream => self.stream.skip().await,
+            Source::File => self.file.skip().await,
         }
     }
 
     async fn play_uri(&mut self, input: String) -> anyhow::Result<Vec<Duration>> {
         tracing::debug!(%input, "Playing URI");
-        let uri = Url::parse(&input)?;
-        match uri.scheme() {
-            "https" if uri.host_str() == Some("open.spotify.com") =>self.stream.play(input).await,
-            "spotify" => self.stream.play(input).await,
-            "file" => self.file.play(input).await,
-            _ => anyhow::bail!("Unknown scheme: {}", uri.scheme()),
+        let source = Source::try_from(input.as_str())?;
+        let (uri, options) = options::parse(&input)?;
+
+        let songs = self.start(source, uri, &options).await?;
+
+        if let Err(e) = self.level(source, options.volume).await {
+            tracing::warn!(%e, "Failed to set the volume");
+        }
+
+        Ok(songs)
+    }
+
+    async fn start(
+        &mut self,
+        source: Source,
+        uri: String,
+        options: &Options,
+    ) -> anyhow::Result<Vec<Duration>> {
+        if options.resume
+            && !self.no_resume.contains(&source)
+            && let Some(bookmark) = self.bookmarks.get(&uri)
+        {
+            tracing::info!(%uri, position = ?bookmark.position, "Resuming where the URI stopped");
+            let resumed = match source {
+                Source::Stream => self.stream.play(bookmark.songs, bookmark.position).await,
+                Source::File => self.file.play(bookmark.songs, bookmark.position).await,
+            };
+
+            match resumed {
+                Ok(songs) => return Ok(songs),
+                Err(e) => tracing::warn!(%e, %uri, "Failed to resume, playing from the start"),
+            }
+        }
+
+        let mut songs = match source {
+            Source::Stream => self.stream.songs(uri).await?,
+            Source::File => self.file.songs(uri).await?,
+        };
+
+        let order = match options.order.unwrap_or(self.order) {
+            // Only file names have numbers to sort by.
+            Order::Natural if source == Source::Stream => Order::Sequential,
+            order => order,
+        };
+        tracing::debug!(?order, "Arranging songs");
+        order.arrange(&mut songs, &self.history, &mut rand::rng());
+
+        match source {
+            Source::Stream => self.stream.play(songs, Duration::ZERO).await,
+            Source::File => self.file.play(songs, Duration::ZERO).await,
+        }
+    }
+
+    /// Bookmarks where the URI playing is, so it resumes from there the next time it plays.
+    async fn remember(&mut self) {
+        let Some(last) = self.last.clone() else {
+            return;
+        };
+
+        if let Err(e) = self.bookmark(&last).await {
+            tracing::warn!(%e, uri = %last, "Failed to bookmark the URI");
+        }
+    }
+
+    async fn bookmark(&mut self, input: &str) -> anyhow::Result<()> {
+        let source = Source::try_from(input)?;
+        let (uri, options) = options::parse(input)?;
+        if !options.resume || self.no_resume.contains(&source) {
+            return Ok(());
+        }
+
+        let bookmark = match source {
+            Source::Stream => self.stream.bookmark().await?,
+            Source::File => self.file.bookmark(),
+        };
+
+        self.bookmarks.set(uri, bookmark)
+    }
+
+    async fn control(&mut self, command: Command) -> anyhow::Result<()> {
+        tracing::debug!(?command, "Controlling playback");
+        match command {
+            Command::Play(input) => anyhow::bail!("Cannot play {input} as a control command"),
+            Command::Remove(uri) => self.remove(&uri).await,
+            Command::Pause => self.pause().await,
+            Command::Resume => self.resume().await,
+            Command::Skip => {
+                let Some(last) = self.last.clone() else {
+                    anyhow::bail!("Missing last url field");
+                };
+
+                if self.skip(&last).await? {
+                    self.tracker.start();
+                }
+
+                Ok(())
+            }
+            Command::Previous => {
+                match self.last.as_deref().map(Source::try_from).transpose()? {
+                    Some(Source::Stream) => self.stream.previous().await?,
+                    Some(Source::File) => self.file.previous().await?,
+                    None => anyhow::bail!("Missing last url field"),
+                }
+
+                self.tracker.previous();
+                Ok(())
+            }
+            Command::Restart => match self.last.as_deref().map(Source::try_from).transpose()? {
+                Some(Source::Stream) => self.stream.restart().await,
+                Some(Source::File) => self.file.restart().await,
+                None => anyhow::bail!("Missing last url field"),
+            },
+            Command::Seek(position) => {
+                match self.last.as_deref().map(Source::try_from).transpose()? {
+                    Some(Source::Stream) => self.stream.seek(position).await,
+                    Some(Source::File) => self.file.seek(position).await,
+                    None => anyhow::bail!("Missing last url field"),
+                }
+            }
+            Command::Stop => self.stop().await,
+            Command::Volume(volume) => self.change_volume(volume).await,
+            Command::ToggleShuffle => {
+                std::mem::swap(&mut self.order, &mut self.toggled);
+                tracing::info!(order = ?self.order, "Toggled shuffle");
+                Ok(())
+            }
+            Command::Sleep(duration) => {
+                self.sleep = duration.map(|duration| Instant::now() + duration);
+                tracing::info!(?duration, "Set the sleep timer");
+                Ok(())
+            }
+            Command::Enqueue(input) => {
+                Source::try_from(input.as_str())?;
+                tracing::info!(%input, "Queued the URI");
+                self.queue.push(input)
+            }
+            Command::Dequeue(index) => {
+                let input = self.queue.remove(index)?;
+                tracing::info!(%input, "Removed the URI from the queue");
+                Ok(())
+            }
+            Command::Reorder { index, position } => self.queue.reorder(index, position),
+            Command::ToggleEnqueue => {
+                self.enqueue = !self.enqueue;
+                tracing::info!(enqueue = self.enqueue, "Toggled queueing cards");
+                Ok(())
+            }
+        }
+    }
+
+    async fn change_volume(&mut self, volume: Volume) -> anyhow::Result<()> {
+        let Some(source) = self.last.as_deref().map(Source::try_from).transpose()? else {
+            // Nothing played yet, so change the volume both backends start with.
+            let level = volume.apply(self.file.volume().await?, &mut self.muted, self.max_volume);
+            tracing::info!(?volume, level, "Changing the volume");
+
+            self.file.set_volume(level).await?;
+            if let Err(e) = self.stream.set_volume(level).await {
+                tracing::debug!(%e, "Failed to change the volume of the Spotify device");
+            }
+
+            return Ok(());
+        };
+
+        let current = match source {
+            Source::Stream => self.stream.volume().await?,
+            Source::File => self.file.volume().await?,
+        };
+        let level = volume.apply(current, &mut self.muted, self.max_volume);
+        tracing::info!(?volume, level, "Changing the volume");
+
+        self.set_volume(source, level).await
+    }
+
+    /// Sets the volume of the card that started playing, or lowers the volume to the maximum.
+    async fn level(&mut self, source: Source, volume: Option<u8>) -> anyhow::Result<()> {
+        let level = match volume {
+            Some(volume) => volume,
+            None if self.max_volume >= volume::MAX_VOLUME => return Ok(()),
+            None => match source {
+                Source::Stream => self.stream.volume().await?,
+                Source::File => self.file.volume().await?,
+            },
+        };
+
+        self.muted = None;
+        self.set_volume(source, level.min(self.max_volume)).await
+    }
+
+    async fn set_volume(&mut self, source: Source, level: u8) -> anyhow::Result<()> {
+        match source {
+            Source::Stream => self.stream.set_volume(level).await,
+            Source::File => self.file.set_volume(level).await,
+        }
+    }
+
+    async fn stop(&mut self) -> anyhow::Result<()> {
+        tracing::debug!("Stopping playback");
+        self.remember().await;
+
+        if let Some(last) = self.last.take() {
+            match Source::try_from(last.as_str())? {
+                Source::Stream => self.stream.stop().await?,
+                Source::File => self.file.stop().await?,
+            }
+        }
+
+        self.sleep = None;
+        self.tracker.reset(Vec::new());
+        Ok(())
+    }
+
+    async fn resume(&mut self) -> anyhow::Result<()> {
+        tracing::debug!("Resuming playback");
+        // Resuming overrides the pause pending after a card removal.
+        self.grace = None;
+        match self.last.as_deref().map(Source::try_from).transpose()? {
+            Some(Source::Stream) => self.stream.resume().await?,
+            Some(Source::File) => self.file.resume().await?,
+            None => anyhow::bail!("Missing last url field"),
+        }
+
+        self.tracker.start();
+        Ok(())
+    }
+
+    /// Handles the removal of the card with the URI according to the removal policy.
+    /// Removing a card that is not playing, such as a control card, never affects playback.
+    pub async fn remove(&mut self, uri: &str) -> anyhow::Result<()> {
+        if self.last.as_deref() != Some(uri) {
+            return Ok(());
+        }
+
+        match self.removal {
+            Removal::Pause | Removal::Resume => self.pause().await,
+            Removal::Ignore => Ok(()),
+            Removal::Grace(period) => {
+                self.grace = Some(Instant::now() + period);
+                Ok(())
+            }
+        }
+    }
+
+    /// How long to wait before checking what the backend is playing again.
+    fn state_interval(&self) -> Duration {
+        match self.queue.peek() {
+            Some(_) => QUEUED_STATE_INTERVAL,
+            None => STATE_INTERVAL,
+        }
+    }
+
+    /// Plays the next URI in the queue once the backend finished the current one,
+    /// and takes a snapshot of what the player is playing.
+    pub async fn refresh(&mut self) -> anyhow::Result<PlayerState> {
+        let queued = self.queue.entries().to_vec();
+        let Some(last) = self.last.clone() else {
+            return Ok(PlayerState {
+                queued,
+                ..PlayerState::default()
+            });
+        };
+
+        let source = Source::try_from(last.as_str())?;
+        let mut playback = match source {
+            Source::Stream => self.stream.playback().await?,
+            Source::File => self.file.playback().await?,
+        };
+
+        // The volume can be raised outside the jukebox, such as from the Spotify app.
+        if let Some(volume) = playback.volume
+            && volume > self.max_volume
+        {
+            tracing::info!(volume, max = self.max_volume, "Lowering the volume to the maximum");
+            self.set_volume(source, self.max_volume).await?;
+            playback.volume = Some(self.max_volume);
+        }
+
+        if let Some(song) = &playback.song {
+            self.history.played(song);
+        }
+
+        // Every song played, so the next time the URI plays it starts over.
+        if playback.ended {
+            self.bookmarks.set(options::parse(&last)?.0, None)?;
+        }
+
+        if playback.ended
+            && let Some(next) = self.queue.peek().map(str::to_string)
+        {
+            tracing::info!(%next, "Playing the next URI in the queue");
+            let songs = self.play_uri(next.clone()).await?;
+            // The URI stays queued until it plays, so a failure tries it again.
+            self.queue.pop()?;
+
+            self.last = Some(next);
+            self.tracker.reset(songs);
+            return Box::pin(self.refresh()).await;
         }
+
+        Ok(PlayerState {
+            muted: self.muted.is_some(),
+            ..PlayerState::new(source, last, playback, queued)
+        })
     }
 
     pub async fn pause(&mut self) -> anyhow::Result<()> {
         tracing::debug!("Pausing playback");
         match self.last.as_ref() {
             Some(last) => {
-                let uri = Url::parse(last)?;
-                match uri.scheme() {
-                    "https" if uri.host_str() == Some("open.spotify.com") => self.stream.pause().await?,
-                    "spotify" => self.stream.pause().await?,
-                    "file" => self.file.pause().await?,
-                    _ => anyhow::bail!("Unknown scheme: {}", uri.scheme()),
+                match Source::try_from(last.as_str())? {
+                    Source::Stream => self.stream.pause().await?,
+                    Source::File => self.file.pause().await?,
                 }
 
                 self.tracker.pause();
+                self.remember().await;
                 Ok(())
             }
             None => {
@@ -80,31 +506,198 @@ impl Player {
     }
 }
 
+impl TryFrom<&str> for Source {
+    type Error = anyhow::Error;
+
+    fn try_from(input: &str) -> Result<Self, Self::Error> {
+        let uri = Url::parse(input)?;
+        match uri.scheme() {
+            "https" if uri.host_str() == Some("open.spotify.com") => Ok(Source::Stream),
+            "spotify" => Ok(Source::Stream),
+            "file" => Ok(Source::File),
+            _ => anyhow::bail!("Unknown scheme: {}", uri.scheme()),
+        }
+    }
+}
+
+impl From<Action> for Command {
+    fn from(action: Action) -> Self {
+        match action {
+            Action::VolumeUp => Command::Volume(Volume::Change(VOLUME_STEP)),
+            Action::VolumeDown => Command::Volume(Volume::Change(-VOLUME_STEP)),
+            Action::VolumeMute => Command::Volume(Volume::ToggleMute),
+            Action::VolumeSet(volume) => Command::Volume(Volume::Set(volume)),
+            Action::Skip => Command::Skip,
+            Action::Previous => Command::Previous,
+            Action::Restart => Command::Restart,
+            Action::ShuffleToggle => Command::ToggleShuffle,
+            Action::Sleep(duration) => Command::Sleep(duration),
+            Action::Stop => Command::Stop,
+            Action::EnqueueToggle => Command::ToggleEnqueue,
+        }
+    }
+}
+
+/// Whether the player has a backend that can play the URI or it is a control action.
+pub fn supports(input: &str) -> bool {
+    Source::try_from(input).is_ok() || input.parse::<Action>().is_ok()
+}
+
 pub async fn run(
-    mut receiver: Receiver<Option<String>>,
-    stream: spotify::Player,
-    file: local::Player,
+    mut receiver: UnboundedReceiver<Request>,
+    mut player: Player,
+    feedback: Feedback,
+    state: watch::Sender<PlayerState>,
 ) -> anyhow::Result<()> {
-    let mut player = Player::new(stream, file);
+    let mut poll = Instant::now();
 
     loop {
-        receiver.changed().await?;
+        let request = tokio::select! {
+            request = receiver.recv() => match request {
+                Some(request) => request,
+                None => return Ok(()),
+            },
+            _ = sleep_until(player.sleep) => {
+                tracing::info!("Sleep timer expired");
+                player.sleep = None;
 
-        let value = receiver.borrow_and_update().clone();
-
-        tracing::debug!(?value, "received input");
-
-        match value {
-            Some(uri) => {
-                if let Err(e) = player.play(uri).await {
-                    tracing::error!(%e, "Failed to start playback");
+                if let Err(e) = player.pause().await {
+                    tracing::error!(%e, "Failed to pause playback");
                 }
+
+                publish(&mut player, &state).await;
+                continue;
             }
-            None => {
+            _ = sleep_until(player.grace) => {
+                tracing::debug!("Grace period after card removal expired");
+                player.grace = None;
+
                 if let Err(e) = player.pause().await {
                     tracing::error!(%e, "Failed to pause playback");
                 }
+
+                publish(&mut player, &state).await;
+                continue;
+            }
+            _ = tokio::time::sleep_until(poll) => {
+                publish(&mut player, &state).await;
+                poll = Instant::now() + player.state_interval();
+                continue;
             }
         };
+
+        tracing::debug!(command = ?request.command, "received command");
+
+        let play = matches!(request.command, Command::Play(_));
+        let result = player.execute(request.command.clone()).await;
+
+        match &result {
+            Ok(()) if play => feedback.signal(Signal::Success),
+            Ok(()) => {}
+            Err(e) if play => {
+                tracing::error!(%e, "Failed to start playback");
+                feedback.signal(Signal::Failure);
+            }
+            Err(e) => tracing::error!(%e, command = ?request.command, "Failed to control playback"),
+        }
+
+        request.reply(result);
+        publish(&mut player, &state).await;
+    }
+}
+
+/// Publishes the state of the player when it changed, and logs the track when it started.
+async fn publish(player: &mut Player, state: &watch::Sender<PlayerState>) {
+    let snapshot = match player.refresh().await {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            tracing::warn!(%e, "Failed to get the state of the player");
+            return;
+        }
+    };
+
+    state.send_if_modified(|current| {
+        if *current == snapshot {
+            return false;
+        }
+
+        if current.track != snapshot.track
+            && let Some(track) = &snapshot.track
+        {
+            tracing::info!(title = %track.title, artist = ?track.artist, "Now playing");
+        }
+
+        *current = snapshot;
+        true
+    });
+}
+
+/// Waits until the deadline, or forever when there is none.
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token;
+    use std::path::PathBuf;
+
+    fn player() -> Player {
+        let oauth = token::Client::new("client".to_string(), PathBuf::from("token.json"));
+        let stream = spotify::Player::new(spotify::Client::new(oauth, "SE".to_string()), None);
+
+        Player::new(stream, local::Player::new(PathBuf::from("music")), Removal::Pause)
+    }
+
+    #[tokio::test]
+    async fn queues_cards_without_pausing() {
+        let mut player = player();
+        player.enqueue = true;
+        player.last = Some("file:///a".to_string());
+        player.tracker.reset(Vec::new());
+
+        player
+            .execute(Command::Remove("file:///a".to_string()))
+            .await
+            .unwrap();
+        assert!(player.tracker.is_paused());
+
+        player
+            .execute(Command::Play("file:///b".to_string()))
+            .await
+            .unwrap();
+        player
+            .execute(Command::Remove("file:///b".to_string()))
+            .await
+            .unwrap();
+
+        assert!(!player.tracker.is_paused());
+        assert_eq!(player.last.as_deref(), Some("file:///a"));
+        assert_eq!(player.queue.entries(), ["file:///b"]);
+    }
+
+    #[test]
+    fn supports_spotify_and_file_uris() {
+        assert!(supports("spotify:album:123"));
+        assert!(supports("https://open.spotify.com/playlist/abc"));
+        assert!(supports("file:///stories"));
+    }
+
+    #[test]
+    fn supports_control_uris() {
+        assert!(supports("jukebox:volume/up"));
+        assert!(supports("jukebox:sleep/30m"));
+        assert!(!supports("jukebox:unknown"));
+    }
+
+    #[test]
+    fn does_not_support_other_uris() {
+        assert!(!supports("https://example.com/album/123"));
+        assert!(!supports("mailto:jukebox@example.com"));
+        assert!(!supports(""));
     }
 }
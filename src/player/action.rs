@@ -0,0 +1,154 @@
+use crate::player::volume::parse_percent;
+use anyhow::anyhow;
+use std::str::FromStr;
+use std::time::Duration;
+use url::Url;
+
+// The URI scheme of control cards.
+pub const SCHEME: &str = "jukebox";
+
+/// A control action encoded in a `jukebox:` URI, such as `jukebox:volume/up`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    VolumeUp,
+    VolumeDown,
+    /// Mute, or unmute when muted.
+    VolumeMute,
+    VolumeSet(u8),
+    Skip,
+    Previous,
+    /// Play the current song from its start.
+    Restart,
+    ShuffleToggle,
+    /// Pause after the duration, or cancel the sleep timer when none.
+    Sleep(Option<Duration>),
+    Stop,
+    /// Queue the cards presented after it instead of playing them right away, or stop queueing.
+    EnqueueToggle,
+}
+
+impl TryFrom<&Url> for Action {
+    type Error = anyhow::Error;
+
+    fn try_from(uri: &Url) -> Result<Self, Self::Error> {
+        if uri.scheme() != SCHEME {
+            return Err(anyhow!("Unknown scheme: {}", uri.scheme()));
+        }
+
+        let segments: Vec<&str> = uri.path().split('/').collect();
+
+        match segments.as_slice() {
+            ["volume", "up"] => Ok(Action::VolumeUp),
+            ["volume", "down"] => Ok(Action::VolumeDown),
+            ["volume", "mute"] => Ok(Action::VolumeMute),
+            ["volume", volume] => Ok(Action::VolumeSet(parse_percent(volume)?)),
+            ["skip"] => Ok(Action::Skip),
+            ["previous"] => Ok(Action::Previous),
+            ["restart"] => Ok(Action::Restart),
+            ["shuffle", "toggle"] => Ok(Action::ShuffleToggle),
+            ["sleep", "off"] => Ok(Action::Sleep(None)),
+            ["sleep", duration] => Ok(Action::Sleep(Some(parse_duration(duration)?))),
+            ["stop"] => Ok(Action::Stop),
+            ["queue", "toggle"] => Ok(Action::EnqueueToggle),
+            _ => Err(anyhow!("Unknown action: {}", uri.path())),
+        }
+    }
+}
+
+impl FromStr for Action {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Action::try_from(&Url::parse(s)?)
+    }
+}
+
+/// Parses a duration made up of a whole number and a unit of `ms`, `s`, `m` or `h`, such as `30m`.
+pub(crate) fn parse_duration(input: &str) -> anyhow::Result<Duration> {
+    let split = input
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| anyhow!("Missing unit in duration: {input}"))?;
+    let (value, unit) = input.split_at(split);
+    let value: u64 = value.parse()?;
+
+    match unit {
+        "ms" => Ok(Duration::from_millis(value)),
+        "s" => Ok(Duration::from_secs(value)),
+        "m" => Ok(Duration::from_secs(value * 60)),
+        "h" => Ok(Duration::from_secs(value * 60 * 60)),
+        _ => Err(anyhow!("Unknown unit in duration: {input}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_actions() {
+        assert_eq!(
+            "jukebox:volume/up".parse::<Action>().unwrap(),
+            Action::VolumeUp
+        );
+        assert_eq!(
+            "jukebox:volume/down".parse::<Action>().unwrap(),
+            Action::VolumeDown
+        );
+        assert_eq!(
+            "jukebox:volume/mute".parse::<Action>().unwrap(),
+            Action::VolumeMute
+        );
+        assert_eq!(
+            "jukebox:volume/40".parse::<Action>().unwrap(),
+            Action::VolumeSet(40)
+        );
+        assert_eq!("jukebox:skip".parse::<Action>().unwrap(), Action::Skip);
+        assert_eq!(
+            "jukebox:previous".parse::<Action>().unwrap(),
+            Action::Previous
+        );
+        assert_eq!(
+            "jukebox:restart".parse::<Action>().unwrap(),
+            Action::Restart
+        );
+        assert_eq!(
+            "jukebox:shuffle/toggle".parse::<Action>().unwrap(),
+            Action::ShuffleToggle
+        );
+        assert_eq!("jukebox:stop".parse::<Action>().unwrap(), Action::Stop);
+        assert_eq!(
+            "jukebox:queue/toggle".parse::<Action>().unwrap(),
+            Action::EnqueueToggle
+        );
+        assert_eq!(
+            "jukebox:sleep/off".parse::<Action>().unwrap(),
+            Action::Sleep(None)
+        );
+    }
+
+    #[test]
+    fn parses_sleep_durations() {
+        assert_eq!(
+            "jukebox:sleep/30m".parse::<Action>().unwrap(),
+            Action::Sleep(Some(Duration::from_secs(30 * 60)))
+        );
+        assert_eq!(
+            "jukebox:sleep/45s".parse::<Action>().unwrap(),
+            Action::Sleep(Some(Duration::from_secs(45)))
+        );
+        assert_eq!(parse_duration("500ms").unwrap(), Duration::from_millis(500));
+        assert_eq!(
+            "jukebox:sleep/1h".parse::<Action>().unwrap(),
+            Action::Sleep(Some(Duration::from_secs(60 * 60)))
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_actions() {
+        assert!("jukebox:volume/sideways".parse::<Action>().is_err());
+        assert!("jukebox:volume/150".parse::<Action>().is_err());
+        assert!("jukebox:sleep/30".parse::<Action>().is_err());
+        assert!("jukebox:sleep/m".parse::<Action>().is_err());
+        assert!("spotify:track:1".parse::<Action>().is_err());
+    }
+}
@@ -0,0 +1,104 @@
+use crate::store::Store;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::Duration;
+
+/// A song a backend plays, identified by its Spotify URI or file path.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Song {
+    pub id: String,
+    pub duration: Duration,
+    /// The album, or the folder for files, the song belongs to.
+    #[serde(default)]
+    pub album: String,
+}
+
+/// Where a URI stopped playing: the song it stopped in and the songs after it, in order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub songs: Vec<Song>,
+    pub position: Duration,
+}
+
+/// Remembers where each URI stopped playing, shared by the players of every zone.
+#[derive(Clone, Default)]
+pub struct Bookmarks {
+    store: Store,
+    bookmarks: Arc<RwLock<BTreeMap<String, Bookmark>>>,
+}
+
+impl Bookmarks {
+    /// Loads the bookmarks from the file, if any.
+    /// A missing file is treated as no bookmarks.
+    pub fn load(path: Option<PathBuf>) -> anyhow::Result<Self> {
+        let store = Store::new(path);
+        let bookmarks = store.load()?;
+
+        Ok(Self {
+            store,
+            bookmarks: Arc::new(RwLock::new(bookmarks)),
+        })
+    }
+
+    pub fn get(&self, uri: &str) -> Option<Bookmark> {
+        let guard = self
+            .bookmarks
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        guard.get(uri).cloned()
+    }
+
+    /// Remembers where the URI stopped, or forgets it when none, and persists the bookmarks.
+    /// The bookmarks only change once they are saved.
+    pub fn set(&self, uri: String, bookmark: Option<Bookmark>) -> anyhow::Result<()> {
+        let mut guard = self
+            .bookmarks
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        let mut bookmarks = guard.clone();
+
+        let modified = match bookmark {
+            Some(bookmark) => bookmarks.insert(uri, bookmark.clone()) != Some(bookmark),
+            None => bookmarks.remove(&uri).is_some(),
+        };
+        if !modified {
+            return Ok(());
+        }
+
+        self.store.save(&bookmarks)?;
+        *guard = bookmarks;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bookmark(position: u64) -> Bookmark {
+        Bookmark {
+            songs: vec![Song {
+                id: "spotify:track:1".to_string(),
+                duration: Duration::from_secs(180),
+                album: "spotify:album:1".to_string(),
+            }],
+            position: Duration::from_secs(position),
+        }
+    }
+
+    #[test]
+    fn remembers_and_forgets_uris() {
+        let bookmarks = Bookmarks::default();
+
+        bookmarks
+            .set("spotify:album:1".to_string(), Some(bookmark(42)))
+            .unwrap();
+        assert_eq!(bookmarks.get("spotify:album:1"), Some(bookmark(42)));
+
+        bookmarks.set("spotify:album:1".to_string(), None).unwrap();
+        assert_eq!(bookmarks.get("spotify:album:1"), None);
+    }
+}
@@ -0,0 +1,138 @@
+use crate::player::Volume;
+use anyhow::anyhow;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+
+/// A command for the player, sent by the card readers and the web interface.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Play the URI, or carry out the control action of a `jukebox:` URI.
+    Play(String),
+    /// The card with the URI was removed, handled according to the removal policy.
+    Remove(String),
+    Pause,
+    Resume,
+    Skip,
+    /// Play the song before the current one.
+    Previous,
+    /// Play the current song from its start.
+    Restart,
+    /// Play the current song from the position.
+    Seek(Duration),
+    Stop,
+    Volume(Volume),
+    ToggleShuffle,
+    /// Pause after the duration, or cancel the sleep timer when none.
+    Sleep(Option<Duration>),
+    /// Play the URI after the current one and the URIs queued before it.
+    Enqueue(String),
+    /// Remove the entry at the index from the queue.
+    Dequeue(usize),
+    /// Move the entry at the index of the queue to the position.
+    Reorder { index: usize, position: usize },
+    /// Switch between queueing presented cards and playing them right away.
+    ToggleEnqueue,
+}
+
+/// A command and where to send whether it succeeded, if anyone is waiting for it.
+pub struct Request {
+    pub(super) command: Command,
+    pub(super) reply: Option<oneshot::Sender<anyhow::Result<()>>>,
+}
+
+impl Request {
+    /// Sends the outcome of the command to the caller, if it is still waiting.
+    pub(super) fn reply(self, result: anyhow::Result<()>) {
+        if let Some(reply) = self.reply {
+            let _ = reply.send(result);
+        }
+    }
+}
+
+/// Sends commands to a player in order, without coalescing them.
+#[derive(Clone)]
+pub struct Commands(mpsc::UnboundedSender<Request>);
+
+impl Commands {
+    /// Sends the command without waiting for the player to carry it out.
+    pub fn send(&self, command: Command) -> anyhow::Result<()> {
+        self.0
+            .send(Request {
+                command,
+                reply: None,
+            })
+            .map_err(|_| anyhow!("The player has stopped"))
+    }
+
+    /// Sends the command and waits for the player to carry it out.
+    pub async fn execute(&self, command: Command) -> anyhow::Result<()> {
+        let (reply, outcome) = oneshot::channel();
+
+        self.0
+            .send(Request {
+                command,
+                reply: Some(reply),
+            })
+            .map_err(|_| anyhow!("The player has stopped"))?;
+
+        outcome
+            .await
+            .map_err(|_| anyhow!("The player stopped before carrying out the command"))?
+    }
+
+    #[cfg(test)]
+    pub fn same_channel(&self, other: &Commands) -> bool {
+        self.0.same_channel(&other.0)
+    }
+}
+
+/// Creates the channel for the commands to a player.
+pub fn channel() -> (Commands, mpsc::UnboundedReceiver<Request>) {
+    let (sender, receiver) = mpsc::unbounded_channel();
+
+    (Commands(sender), receiver)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn keeps_repeated_commands() {
+        let (commands, mut receiver) = channel();
+
+        commands.send(Command::Skip).unwrap();
+        commands.send(Command::Skip).unwrap();
+
+        assert_eq!(receiver.recv().await.unwrap().command, Command::Skip);
+        assert_eq!(receiver.recv().await.unwrap().command, Command::Skip);
+    }
+
+    #[tokio::test]
+    async fn replies_with_the_outcome() {
+        let (commands, mut receiver) = channel();
+
+        tokio::spawn(async move {
+            while let Some(request) = receiver.recv().await {
+                let result = match request.command {
+                    Command::Pause => Ok(()),
+                    _ => Err(anyhow!("Nothing is playing")),
+                };
+
+                request.reply(result);
+            }
+        });
+
+        assert!(commands.execute(Command::Pause).await.is_ok());
+        assert!(commands.execute(Command::Resume).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn fails_when_the_player_has_stopped() {
+        let (commands, receiver) = channel();
+        drop(receiver);
+
+        assert!(commands.send(Command::Stop).is_err());
+        assert!(commands.execute(Command::Stop).await.is_err());
+    }
+}
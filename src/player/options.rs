@@ -0,0 +1,117 @@
+use crate::player::Order;
+use crate::player::volume::parse_percent;
+use anyhow::anyhow;
+use clap::ValueEnum;
+use url::form_urlencoded;
+
+/// How to play a URI, set by query parameters on the card such as
+/// `file:///stories?resume=off&order=natural`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+    /// Whether to continue from where the URI stopped playing last time.
+    pub resume: bool,
+    /// The order to play the songs in, instead of the default order of the player.
+    pub order: Option<Order>,
+    /// The volume in percent to start playing at.
+    pub volume: Option<u8>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            resume: true,
+            order: None,
+            volume: None,
+        }
+    }
+}
+
+/// Splits the options from the URI, keeping the query parameters the backends need.
+pub fn parse(input: &str) -> anyhow::Result<(String, Options)> {
+    let Some((uri, query)) = input.split_once('?') else {
+        return Ok((input.to_string(), Options::default()));
+    };
+
+    let mut options = Options::default();
+    let mut others = form_urlencoded::Serializer::new(String::new());
+    let mut kept = false;
+
+    for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+        match key.as_ref() {
+            "resume" => options.resume = parse_switch(&value)?,
+            "volume" => options.volume = Some(parse_percent(&value)?),
+            "order" => options.order = Some(Order::from_str(&value, true).map_err(|e| anyhow!(e))?),
+            _ => {
+                others.append_pair(&key, &value);
+                kept = true;
+            }
+        }
+    }
+
+    match kept {
+        true => Ok((format!("{uri}?{}", others.finish()), options)),
+        false => Ok((uri.to_string(), options)),
+    }
+}
+
+fn parse_switch(value: &str) -> anyhow::Result<bool> {
+    match value {
+        "on" | "true" => Ok(true),
+        "off" | "false" => Ok(false),
+        _ => Err(anyhow!("Expected on or off, got {value:?}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_uri_without_options() {
+        let (uri, options) = parse("spotify:album:1").unwrap();
+
+        assert_eq!(uri, "spotify:album:1");
+        assert_eq!(options, Options::default());
+    }
+
+    #[test]
+    fn splits_options_from_uri() {
+        let (uri, options) = parse("file:///stories?resume=off").unwrap();
+
+        assert_eq!(uri, "file:///stories");
+        assert!(!options.resume);
+    }
+
+    #[test]
+    fn keeps_other_query_parameters() {
+        let (uri, options) = parse("https://open.spotify.com/album/1?si=abc&resume=on").unwrap();
+
+        assert_eq!(uri, "https://open.spotify.com/album/1?si=abc");
+        assert!(options.resume);
+    }
+
+    #[test]
+    fn parses_order() {
+        let (uri, options) = parse("file:///stories?order=natural&resume=off").unwrap();
+        assert_eq!(uri, "file:///stories");
+        assert_eq!(options.order, Some(Order::Natural));
+
+        let (_, options) = parse("spotify:playlist:1?order=shuffle-by-album").unwrap();
+        assert_eq!(options.order, Some(Order::ShuffleByAlbum));
+    }
+
+    #[test]
+    fn parses_volume() {
+        let (uri, options) = parse("spotify:album:1?volume=30").unwrap();
+
+        assert_eq!(uri, "spotify:album:1");
+        assert_eq!(options.volume, Some(30));
+    }
+
+    #[test]
+    fn rejects_invalid_options() {
+        assert!(parse("spotify:album:1?resume=maybe").is_err());
+        assert!(parse("spotify:album:1?order=backwards").is_err());
+        assert!(parse("spotify:album:1?volume=101").is_err());
+    }
+}
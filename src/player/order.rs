@@ -0,0 +1,273 @@
+use crate::player::Song;
+use rand::Rng;
+use rand::prelude::SliceRandom;
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+
+/// The order to play the songs of a URI in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Order {
+    /// The order of the album or playlist, or of the paths for files.
+    Sequential,
+    /// Files sorted with the numbers in their names compared by value, so `2` comes before `10`.
+    /// Streams keep the order of the album or playlist.
+    Natural,
+    Shuffle,
+    /// The albums, or folders for files, in random order, each played in album order.
+    ShuffleByAlbum,
+    /// Shuffled so the songs played recently are more likely to come last.
+    Weighted,
+}
+
+impl Order {
+    /// Arranges the songs, which come in the order of the album, playlist or paths.
+    pub fn arrange(self, songs: &mut [Song], recent: &History, rng: &mut impl Rng) {
+        match self {
+            Order::Sequential => {}
+            Order::Natural => songs.sort_by(|a, b| natural(&a.id, &b.id)),
+            Order::Shuffle => songs.shuffle(rng),
+            Order::ShuffleByAlbum => {
+                let mut albums: Vec<&str> = Vec::new();
+                for song in songs.iter() {
+                    if !albums.contains(&song.album.as_str()) {
+                        albums.push(&song.album);
+                    }
+                }
+                albums.shuffle(rng);
+
+                let ranks: Vec<usize> = songs
+                    .iter()
+                    .map(|song| albums.iter().position(|album| *album == song.album))
+                    .map(Option::unwrap_or_default)
+                    .collect();
+                let mut ranked: Vec<(usize, Song)> =
+                    ranks.into_iter().zip(songs.to_vec()).collect();
+                ranked.sort_by_key(|(rank, _)| *rank);
+
+                for (slot, (_, song)) in songs.iter_mut().zip(ranked) {
+                    *slot = song;
+                }
+            }
+            Order::Weighted => {
+                // Weighted random sampling, where the song with the highest key of
+                // `u ^ (1 / weight)` for a uniform `u` in (0, 1) comes first.
+                let mut keyed: Vec<(f64, Song)> = songs
+                    .iter()
+                    .map(|song| {
+                        let key = rng.random::<f64>().powf(1.0 / recent.weight(&song.id));
+                        (key, song.clone())
+                    })
+                    .collect();
+                keyed.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+
+                for (slot, (_, song)) in songs.iter_mut().zip(keyed) {
+                    *slot = song;
+                }
+            }
+        }
+    }
+}
+
+/// The songs played most recently, with the latest at the back.
+#[derive(Debug, Default)]
+pub struct History {
+    songs: VecDeque<String>,
+}
+
+impl History {
+    // Number of songs to remember.
+    const CAPACITY: usize = 100;
+
+    /// Records the song as playing, unless it already was the latest.
+    pub fn played(&mut self, id: &str) {
+        if self.songs.back().is_some_and(|latest| latest == id) {
+            return;
+        }
+
+        self.songs.retain(|song| song != id);
+        if self.songs.len() == Self::CAPACITY {
+            self.songs.pop_front();
+        }
+
+        self.songs.push_back(id.to_string());
+    }
+
+    /// The weight of the song for a weighted shuffle, smallest for the latest song played.
+    fn weight(&self, id: &str) -> f64 {
+        match self.songs.iter().rev().position(|song| song == id) {
+            Some(age) => (age + 1) as f64 / (Self::CAPACITY + 1) as f64,
+            None => 1.0,
+        }
+    }
+}
+
+/// Compares the strings with runs of digits compared by their value.
+pub fn natural(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a, b);
+
+    loop {
+        let (Some(x), Some(y)) = (a.chars().next(), b.chars().next()) else {
+            return a.len().cmp(&b.len());
+        };
+
+        if x.is_ascii_digit() && y.is_ascii_digit() {
+            let (left, rest_a) = split_digits(a);
+            let (right, rest_b) = split_digits(b);
+            let (left_trimmed, right_trimmed) =
+                (left.trim_start_matches('0'), right.trim_start_matches('0'));
+
+            let ordering = left_trimmed
+                .len()
+                .cmp(&right_trimmed.len())
+                .then_with(|| left_trimmed.cmp(right_trimmed))
+                .then_with(|| left.len().cmp(&right.len()));
+            if ordering.is_ne() {
+                return ordering;
+            }
+
+            (a, b) = (rest_a, rest_b);
+        } else {
+            let ordering = x.cmp(&y);
+            if ordering.is_ne() {
+                return ordering;
+            }
+
+            (a, b) = (&a[x.len_utf8()..], &b[y.len_utf8()..]);
+        }
+    }
+}
+
+fn split_digits(s: &str) -> (&str, &str) {
+    s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use std::time::Duration;
+
+    fn songs(ids: &[(&str, &str)]) -> Vec<Song> {
+        ids.iter()
+            .map(|(album, id)| Song {
+                id: id.to_string(),
+                duration: Duration::ZERO,
+                album: album.to_string(),
+            })
+            .collect()
+    }
+
+    fn ids(songs: &[Song]) -> Vec<&str> {
+        songs.iter().map(|song| song.id.as_str()).collect()
+    }
+
+    #[test]
+    fn compares_numbers_by_value() {
+        assert_eq!(natural("Track 2.mp3", "Track 10.mp3"), Ordering::Less);
+        assert_eq!(natural("Track 10.mp3", "Track 9.mp3"), Ordering::Greater);
+        assert_eq!(natural("Track 02.mp3", "Track 2.mp3"), Ordering::Greater);
+        assert_eq!(natural("b", "a10"), Ordering::Greater);
+        assert_eq!(natural("a", "a1"), Ordering::Less);
+        assert_eq!(natural("Ä 1", "Ä 1"), Ordering::Equal);
+    }
+
+    #[test]
+    fn sorts_files_naturally() {
+        let mut songs = songs(&[
+            ("", "/m/10 End.mp3"),
+            ("", "/m/1 Start.mp3"),
+            ("", "/m/2 Middle.mp3"),
+        ]);
+
+        Order::Natural.arrange(
+            &mut songs,
+            &History::default(),
+            &mut StdRng::seed_from_u64(1),
+        );
+
+        assert_eq!(
+            ids(&songs),
+            ["/m/1 Start.mp3", "/m/2 Middle.mp3", "/m/10 End.mp3"]
+        );
+    }
+
+    #[test]
+    fn keeps_sequential_order() {
+        let mut songs = songs(&[("", "b"), ("", "a"), ("", "c")]);
+
+        Order::Sequential.arrange(
+            &mut songs,
+            &History::default(),
+            &mut StdRng::seed_from_u64(1),
+        );
+
+        assert_eq!(ids(&songs), ["b", "a", "c"]);
+    }
+
+    #[test]
+    fn keeps_albums_together() {
+        let original = songs(&[
+            ("x", "x/1"),
+            ("y", "y/1"),
+            ("x", "x/2"),
+            ("z", "z/1"),
+            ("y", "y/2"),
+            ("x", "x/10"),
+        ]);
+
+        for seed in 0..10 {
+            let mut songs = original.clone();
+            Order::ShuffleByAlbum.arrange(
+                &mut songs,
+                &History::default(),
+                &mut StdRng::seed_from_u64(seed),
+            );
+
+            let albums: Vec<&str> = songs.iter().map(|song| song.album.as_str()).collect();
+            let changes = albums.windows(2).filter(|pair| pair[0] != pair[1]).count();
+            assert_eq!(changes, 2, "{albums:?}");
+
+            let x: Vec<&str> = ids(&songs)
+                .into_iter()
+                .filter(|id| id.starts_with('x'))
+                .collect();
+            assert_eq!(x, ["x/1", "x/2", "x/10"]);
+        }
+    }
+
+    #[test]
+    fn plays_recent_songs_last() {
+        let original = songs(&[("", "a"), ("", "b"), ("", "c"), ("", "d")]);
+        let mut history = History::default();
+        history.played("a");
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut first = 0;
+        for _ in 0..200 {
+            let mut songs = original.clone();
+            Order::Weighted.arrange(&mut songs, &history, &mut rng);
+
+            if songs[0].id == "a" {
+                first += 1;
+            }
+        }
+
+        // An unweighted shuffle puts each song first a quarter of the time.
+        assert!(first < 10, "{first}");
+    }
+
+    #[test]
+    fn remembers_latest_songs() {
+        let mut history = History::default();
+
+        history.played("a");
+        history.played("b");
+        history.played("a");
+        history.played("a");
+
+        assert_eq!(history.songs, ["b", "a"]);
+        assert!(history.weight("a") < history.weight("b"));
+        assert_eq!(history.weight("c"), 1.0);
+    }
+}
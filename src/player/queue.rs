@@ -0,0 +1,117 @@
+use crate::store::Store;
+use anyhow::anyhow;
+use std::path::PathBuf;
+
+/// The URIs to play after the current one, persisted so they survive restarts.
+#[derive(Debug, Default)]
+pub struct Queue {
+    store: Store,
+    entries: Vec<String>,
+}
+
+impl Queue {
+    /// Loads the queue from the file, if any.
+    /// A missing file is treated as an empty queue.
+    pub fn load(path: Option<PathBuf>) -> anyhow::Result<Self> {
+        let store = Store::new(path);
+        let entries = store.load()?;
+
+        Ok(Self { store, entries })
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// The next URI to play, left in the queue until it is popped.
+    pub fn peek(&self) -> Option<&str> {
+        self.entries.first().map(String::as_str)
+    }
+
+    /// Appends the URI to the end of the queue.
+    pub fn push(&mut self, uri: String) -> anyhow::Result<()> {
+        self.update(|entries| entries.push(uri))
+    }
+
+    /// Takes the next URI to play from the front of the queue.
+    pub fn pop(&mut self) -> anyhow::Result<Option<String>> {
+        if self.entries.is_empty() {
+            return Ok(None);
+        }
+
+        self.update(|entries| Some(entries.remove(0)))
+    }
+
+    pub fn remove(&mut self, index: usize) -> anyhow::Result<String> {
+        self.check(index)?;
+        self.update(|entries| entries.remove(index))
+    }
+
+    /// Moves the entry at the index to the position, shifting the entries in between.
+    pub fn reorder(&mut self, index: usize, position: usize) -> anyhow::Result<()> {
+        self.check(index)?;
+        self.check(position)?;
+
+        self.update(|entries| {
+            let uri = entries.remove(index);
+            entries.insert(position, uri);
+        })
+    }
+
+    fn check(&self, index: usize) -> anyhow::Result<()> {
+        match index < self.entries.len() {
+            true => Ok(()),
+            false => Err(anyhow!("No entry {index} in a queue of {}", self.entries.len())),
+        }
+    }
+
+    /// Changes a copy of the entries and keeps it once it is saved,
+    /// so the queue stays as it was when saving fails.
+    fn update<R>(&mut self, change: impl FnOnce(&mut Vec<String>) -> R) -> anyhow::Result<R> {
+        let mut entries = self.entries.clone();
+        let result = change(&mut entries);
+
+        self.store.save(&entries)?;
+        self.entries = entries;
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue(entries: &[&str]) -> Queue {
+        Queue {
+            store: Store::default(),
+            entries: entries.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    #[test]
+    fn plays_entries_in_order() {
+        let mut queue = queue(&["spotify:album:1"]);
+
+        queue.push("file:///stories".to_string()).unwrap();
+
+        assert_eq!(queue.peek(), Some("spotify:album:1"));
+        assert_eq!(queue.pop().unwrap().as_deref(), Some("spotify:album:1"));
+        assert_eq!(queue.pop().unwrap().as_deref(), Some("file:///stories"));
+        assert_eq!(queue.pop().unwrap(), None);
+    }
+
+    #[test]
+    fn reorders_and_removes_entries() {
+        let mut queue = queue(&["a:1", "a:2", "a:3"]);
+
+        queue.reorder(2, 0).unwrap();
+        assert_eq!(queue.entries(), ["a:3", "a:1", "a:2"]);
+
+        assert_eq!(queue.remove(1).unwrap(), "a:1");
+        assert_eq!(queue.entries(), ["a:3", "a:2"]);
+
+        assert!(queue.remove(2).is_err());
+        assert!(queue.reorder(0, 2).is_err());
+    }
+}
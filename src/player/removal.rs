@@ -0,0 +1,82 @@
+use crate::player::action::parse_duration;
+use anyhow::anyhow;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// What the player does when a card is removed from the reader.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Removal {
+    /// Pause playback; re-inserting the same card skips to the next song.
+    #[default]
+    Pause,
+    /// Keep playing; cards only start playback when tapped.
+    Ignore,
+    /// Pause playback; re-inserting the same card continues where it left off.
+    Resume,
+    /// Keep playing for the duration before pausing, unless the same card is re-inserted.
+    Grace(Duration),
+}
+
+impl FromStr for Removal {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some(("grace", duration)) => Ok(Removal::Grace(parse_duration(duration)?)),
+            None if s == "pause" => Ok(Removal::Pause),
+            None if s == "ignore" => Ok(Removal::Ignore),
+            None if s == "resume" => Ok(Removal::Resume),
+            _ => Err(anyhow!(
+                "Unknown removal policy {s:?}, expected pause, ignore, resume or grace:<duration>"
+            )),
+        }
+    }
+}
+
+impl Display for Removal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Removal::Pause => write!(f, "pause"),
+            Removal::Ignore => write!(f, "ignore"),
+            Removal::Resume => write!(f, "resume"),
+            Removal::Grace(duration) => write!(f, "grace:{}s", duration.as_secs()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_policies() {
+        assert_eq!("pause".parse::<Removal>().unwrap(), Removal::Pause);
+        assert_eq!("ignore".parse::<Removal>().unwrap(), Removal::Ignore);
+        assert_eq!("resume".parse::<Removal>().unwrap(), Removal::Resume);
+        assert_eq!(
+            "grace:30s".parse::<Removal>().unwrap(),
+            Removal::Grace(Duration::from_secs(30))
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_policies() {
+        assert!("stop".parse::<Removal>().is_err());
+        assert!("grace".parse::<Removal>().is_err());
+        assert!("grace:soon".parse::<Removal>().is_err());
+        assert!("pause:30s".parse::<Removal>().is_err());
+    }
+
+    #[test]
+    fn displays_parseable_policies() {
+        for removal in [
+            Removal::Pause,
+            Removal::Ignore,
+            Removal::Resume,
+            Removal::Grace(Duration::from_secs(90)),
+        ] {
+            assert_eq!(removal.to_string().parse::<Removal>().unwrap(), removal);
+        }
+    }
+}
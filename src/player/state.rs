@@ -0,0 +1,125 @@
+use serde::{Serialize, Serializer};
+use std::time::Duration;
+
+/// The backend that plays a URI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    Stream,
+    File,
+}
+
+/// The track a backend is playing.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Track {
+    pub title: String,
+    pub artist: Option<String>,
+    /// The URL of the cover art.
+    pub art: Option<String>,
+    #[serde(rename = "duration_ms", serialize_with = "milliseconds")]
+    pub duration: Duration,
+}
+
+/// What a backend is playing, as reported by the backend itself.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Playback {
+    pub track: Option<Track>,
+    /// The id of the song playing, as in [`Song`](crate::player::Song).
+    pub song: Option<String>,
+    pub position: Duration,
+    pub paused: bool,
+    /// The volume in percent, when the backend has one.
+    pub volume: Option<u8>,
+    /// Number of tracks left to play after the current one.
+    pub queue: usize,
+    /// Whether the backend played every track, so the next URI in the queue can start.
+    /// Backends that cannot tell for sure guess from what they are playing.
+    pub ended: bool,
+}
+
+/// A snapshot of the player, published whenever it changes.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PlayerState {
+    pub source: Option<Source>,
+    pub uri: Option<String>,
+    pub track: Option<Track>,
+    #[serde(rename = "position_ms", serialize_with = "milliseconds")]
+    pub position: Duration,
+    pub paused: bool,
+    /// The volume in percent, when the backend has one.
+    pub volume: Option<u8>,
+    /// Whether the volume was muted, to be restored when unmuting.
+    pub muted: bool,
+    pub queue: usize,
+    /// The URIs queued to play after this one.
+    pub queued: Vec<String>,
+}
+
+impl PlayerState {
+    pub fn new(source: Source, uri: String, playback: Playback, queued: Vec<String>) -> Self {
+        Self {
+            source: Some(source),
+            uri: Some(uri),
+            track: playback.track,
+            position: playback.position,
+            paused: playback.paused,
+            volume: playback.volume,
+            muted: false,
+            queue: playback.queue,
+            queued,
+        }
+    }
+}
+
+fn milliseconds<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_millis() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serializes_snapshot() {
+        let state = PlayerState::new(
+            Source::File,
+            "file:///stories".to_string(),
+            Playback {
+                track: Some(Track {
+                    title: "Chapter 1".to_string(),
+                    ..Track::default()
+                }),
+                song: Some("/music/stories/Chapter 1.mp3".to_string()),
+                position: Duration::from_millis(1500),
+                paused: true,
+                volume: Some(40),
+                queue: 3,
+                ended: false,
+            },
+            vec!["spotify:album:1".to_string()],
+        );
+
+        assert_eq!(
+            serde_json::to_value(&state).unwrap(),
+            serde_json::json!({
+                "source": "file",
+                "uri": "file:///stories",
+                "track": {"title": "Chapter 1", "artist": null, "art": null, "duration_ms": 0},
+                "position_ms": 1500,
+                "paused": true,
+                "volume": 40,
+                "muted": false,
+                "queue": 3,
+                "queued": ["spotify:album:1"],
+            })
+        );
+    }
+
+    #[test]
+    fn serializes_idle_player() {
+        let state = serde_json::to_value(PlayerState::default()).unwrap();
+
+        assert_eq!(state["source"], serde_json::Value::Null);
+        assert_eq!(state["position_ms"], 0);
+    }
+}
@@ -0,0 +1,117 @@
+use anyhow::anyhow;
+use std::str::FromStr;
+
+// The loudest volume, in percent.
+pub const MAX_VOLUME: u8 = 100;
+
+/// A change to the volume, in percent, the same for every backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Volume {
+    Set(u8),
+    /// Change the volume by the given percentage points.
+    Change(i32),
+    Mute,
+    /// Restore the volume from before muting.
+    Unmute,
+    ToggleMute,
+}
+
+impl Volume {
+    /// The volume to set on the backend, capped at the maximum.
+    /// Muting remembers the current volume, and any other change unmutes.
+    pub fn apply(self, current: u8, muted: &mut Option<u8>, max: u8) -> u8 {
+        let volume = match self {
+            Volume::Set(volume) => {
+                *muted = None;
+                volume
+            }
+            Volume::Change(delta) => {
+                let volume = muted.take().unwrap_or(current) as i32 + delta;
+                volume.clamp(0, MAX_VOLUME as i32) as u8
+            }
+            Volume::Mute => {
+                muted.get_or_insert(current);
+                0
+            }
+            Volume::Unmute => muted.take().unwrap_or(current),
+            Volume::ToggleMute if muted.is_some() => muted.take().unwrap_or(current),
+            Volume::ToggleMute => {
+                *muted = Some(current);
+                0
+            }
+        };
+
+        volume.min(max)
+    }
+}
+
+impl FromStr for Volume {
+    type Err = anyhow::Error;
+
+    /// Parses `mute`, `unmute`, `toggle`, a change such as `+10` or `-10`, or a volume such as `40`.
+    /// Forms encode `+` as a space, so a leading space is a change up as well.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim_end();
+
+        match s {
+            "mute" => Ok(Volume::Mute),
+            "unmute" => Ok(Volume::Unmute),
+            "toggle" => Ok(Volume::ToggleMute),
+            _ if s.starts_with([' ', '+', '-']) => Ok(Volume::Change(s.trim_start().parse()?)),
+            _ => Ok(Volume::Set(parse_percent(s)?)),
+        }
+    }
+}
+
+/// Parses a volume between 0 and 100 percent.
+pub fn parse_percent(input: &str) -> anyhow::Result<u8> {
+    match input.parse::<u8>() {
+        Ok(volume) if volume <= MAX_VOLUME => Ok(volume),
+        _ => Err(anyhow!(
+            "Expected a volume between 0 and {MAX_VOLUME}, got {input:?}"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_volumes() {
+        assert_eq!("40".parse::<Volume>().unwrap(), Volume::Set(40));
+        assert_eq!("+10".parse::<Volume>().unwrap(), Volume::Change(10));
+        assert_eq!(" 10".parse::<Volume>().unwrap(), Volume::Change(10));
+        assert_eq!("-5".parse::<Volume>().unwrap(), Volume::Change(-5));
+        assert_eq!("mute".parse::<Volume>().unwrap(), Volume::Mute);
+        assert_eq!("toggle".parse::<Volume>().unwrap(), Volume::ToggleMute);
+        assert!("101".parse::<Volume>().is_err());
+        assert!("loud".parse::<Volume>().is_err());
+    }
+
+    #[test]
+    fn changes_within_bounds() {
+        let mut muted = None;
+
+        assert_eq!(Volume::Change(10).apply(95, &mut muted, 100), 100);
+        assert_eq!(Volume::Change(-10).apply(5, &mut muted, 100), 0);
+        assert_eq!(Volume::Set(80).apply(20, &mut muted, 60), 60);
+        assert_eq!(Volume::Change(10).apply(55, &mut muted, 60), 60);
+    }
+
+    #[test]
+    fn restores_volume_after_muting() {
+        let mut muted = None;
+
+        assert_eq!(Volume::Mute.apply(40, &mut muted, 100), 0);
+        assert_eq!(muted, Some(40));
+        // Muting again keeps the volume from before the first time.
+        assert_eq!(Volume::Mute.apply(0, &mut muted, 100), 0);
+        assert_eq!(Volume::ToggleMute.apply(0, &mut muted, 100), 40);
+        assert_eq!(muted, None);
+
+        assert_eq!(Volume::ToggleMute.apply(40, &mut muted, 100), 0);
+        assert_eq!(Volume::Change(10).apply(0, &mut muted, 100), 50);
+        assert_eq!(muted, None);
+    }
+}
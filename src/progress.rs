@@ -38,6 +38,15 @@ impl SongTracker {
         self.index = 0;
     }
 
+    /// Moves back to the song before the current one.
+    pub fn previous(&mut self) {
+        self.index = self.index.saturating_sub(1);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.start.is_none()
+    }
+
     pub fn has_next(&self) -> bool {
         self.index < self.songs.len()
     }
@@ -60,19 +69,38 @@ mod tests {
 
     #[test]
     fn pause_does_not_change_index_when_not_started() {
-        let mut tracker = SongTracker::default();
-        tracker.songs = vec![Duration::from_secs(5)];
+        let mut tracker = SongTracker {
+            songs: vec![Duration::from_secs(5)],
+            ..SongTracker::default()
+        };
         tracker.pause();
 
         assert_eq!(tracker.index, 0);
         assert!(tracker.has_next());
     }
 
+    #[test]
+    fn previous_stops_at_first_song() {
+        let mut tracker = SongTracker {
+            songs: vec![Duration::from_secs(5), Duration::from_secs(5)],
+            index: 1,
+            ..SongTracker::default()
+        };
+
+        tracker.previous();
+        assert_eq!(tracker.index, 0);
+
+        tracker.previous();
+        assert_eq!(tracker.index, 0);
+    }
+
     #[test]
     fn pause_moves_to_next_song() {
-        let mut tracker = SongTracker::default();
-        tracker.songs = vec![Duration::from_secs(60)];
-        tracker.start = Some(Instant::now());
+        let mut tracker = SongTracker {
+            songs: vec![Duration::from_secs(60)],
+            start: Some(Instant::now()),
+            ..SongTracker::default()
+        };
         tracker.pause();
 
         assert_eq!(tracker.index, 1);
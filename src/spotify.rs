@@ -1,14 +1,16 @@
 mod client;
+// Mirrors the Spotify Web API, including objects the jukebox does not use yet.
+#[allow(dead_code)]
 pub mod models;
 mod uri;
 pub mod playable;
 
 use std::time::Duration;
 use anyhow::anyhow;
-use rand::prelude::SliceRandom;
 use reqwest::StatusCode;
 
-use crate::spotify::models::StartPlaybackRequest;
+use crate::player::{Bookmark, Playback, Song, Track};
+use crate::spotify::models::{Device, PlaybackState, StartPlaybackRequest};
 pub use playable::Playable;
 pub use crate::spotify::client::Client;
 use crate::spotify::uri::Uri;
@@ -17,6 +19,12 @@ pub struct Player {
     client: Client,
     preferred_device: Option<String>,
     device_id: Option<String>,
+    // The songs started by the last play, in the order they play.
+    songs: Vec<Song>,
+    // Whether the device reported one of the songs since they started.
+    started: bool,
+    // Whether the device reported the last song past its start.
+    finishing: bool,
 }
 
 impl Player {
@@ -25,30 +33,73 @@ impl Player {
             client,
             preferred_device,
             device_id: None,
+            songs: Vec::new(),
+            started: false,
+            finishing: false,
         }
     }
 
-    pub async fn play(&mut self, uri: String) -> anyhow::Result<Vec<Duration>> {
-        if self.preferred_device.is_some() && self.device_id.is_none() {
-            let preferred_device_name = self.preferred_device.clone().unwrap_or_default();
-            self.device_id = Some(self.preferred_device_id(preferred_device_name).await?);
-        }
-
+    /// The songs of the URI, in the order of the album or playlist.
+    pub async fn songs(&mut self, uri: String) -> anyhow::Result<Vec<Song>> {
         let playable = self.resolve_uri(&uri).await?;
-        let mut songs = playable.songs();
+        let songs = playable.songs();
 
         if songs.is_empty() {
             return Err(anyhow!("No songs to play"));
         }
 
-        songs.shuffle(&mut rand::rng());
+        Ok(songs
+            .into_iter()
+            .map(|song| Song {
+                id: song.uri,
+                duration: song.duration,
+                album: song.album,
+            })
+            .collect())
+    }
+
+    /// Plays the songs in order, from the position in the first song.
+    pub async fn play(
+        &mut self,
+        songs: Vec<Song>,
+        position: Duration,
+    ) -> anyhow::Result<Vec<Duration>> {
+        if self.preferred_device.is_some() && self.device_id.is_none() {
+            let preferred_device_name = self.preferred_device.clone().unwrap_or_default();
+            self.device_id = Some(self.preferred_device_id(preferred_device_name).await?);
+        }
 
-        let uris: Vec<String> = songs.iter().map(|song| song.uri.clone()).collect();
-        let request = StartPlaybackRequest::from(uris);
+        let uris: Vec<String> = songs.iter().map(|song| song.id.clone()).collect();
+        let mut request = StartPlaybackRequest::from(uris);
+        request.position_ms = position.as_millis() as u64;
 
         self.client.play(self.device_id.clone(), &request).await?;
 
-        Ok(songs.iter().map(|song| song.duration).collect())
+        let durations = songs.iter().map(|song| song.duration).collect();
+        self.songs = songs;
+        self.started = false;
+        self.finishing = false;
+
+        Ok(durations)
+    }
+
+    /// The song playing on the device and the songs after it, when it is one of ours.
+    pub async fn bookmark(&mut self) -> anyhow::Result<Option<Bookmark>> {
+        let Some(state) = self.state().await? else {
+            return Ok(None);
+        };
+        let Some(item) = state.item else {
+            return Ok(None);
+        };
+
+        Ok(self
+            .songs
+            .iter()
+            .position(|song| song.id == item.uri)
+            .map(|index| Bookmark {
+                songs: self.songs[index..].to_vec(),
+                position: Duration::from_millis(state.progress_ms),
+            }))
     }
 
     pub async fn skip(&mut self) -> anyhow::Result<bool> {
@@ -64,6 +115,25 @@ impl Player {
         }
     }
 
+    pub async fn previous(&mut self) -> anyhow::Result<()> {
+        self.client.skip_to_previous(self.device_id.clone()).await?;
+        Ok(())
+    }
+
+    /// Plays the current song from its start.
+    pub async fn restart(&mut self) -> anyhow::Result<()> {
+        self.client.seek(self.device_id.clone(), Duration::ZERO).await?;
+        self.finishing = false;
+        Ok(())
+    }
+
+    /// Plays the current song from the position.
+    pub async fn seek(&mut self, position: Duration) -> anyhow::Result<()> {
+        self.client.seek(self.device_id.clone(), position).await?;
+        self.finishing = false;
+        Ok(())
+    }
+
     pub async fn pause(&mut self) -> anyhow::Result<()> {
         if let Err(e) = self.client.pause(None).await {
             // Song may not be playing.
@@ -77,6 +147,86 @@ impl Player {
         Ok(())
     }
 
+    pub async fn resume(&mut self) -> anyhow::Result<()> {
+        self.client.resume(self.device_id.clone()).await?;
+        Ok(())
+    }
+
+    pub async fn stop(&mut self) -> anyhow::Result<()> {
+        self.pause().await
+    }
+
+    /// The volume of the device in percent.
+    pub async fn volume(&mut self) -> anyhow::Result<u8> {
+        let Some(state) = self.state().await? else {
+            return Err(anyhow!("The device is not active to get the volume of"));
+        };
+
+        if !state.device.supports_volume {
+            return Err(anyhow!("The device {:?} does not support volume", state.device.name));
+        }
+
+        Ok(state.device.volume_percent.min(100) as u8)
+    }
+
+    pub async fn set_volume(&mut self, percent: u8) -> anyhow::Result<()> {
+        self.client.set_volume(self.device_id.clone(), percent).await?;
+        Ok(())
+    }
+
+    /// The track playing on the device and the number of songs after it.
+    pub async fn playback(&mut self) -> anyhow::Result<Playback> {
+        let Some(state) = self.state().await? else {
+            return Ok(Playback::default());
+        };
+
+        let Some(item) = state.item else {
+            return Ok(Playback {
+                paused: !state.is_playing,
+                volume: volume(&state.device),
+                ..Playback::default()
+            });
+        };
+
+        // The API does not tell when the songs ran out, so this is a guess from what changed:
+        // Spotify stops at the start of the last song once it played past its start,
+        // or moves on to something else, such as autoplay, after playing one of the songs.
+        // Until the device reports one of the songs, it may still be playing what came before.
+        let index = self.songs.iter().position(|song| song.id == item.uri);
+        let last = index.is_some_and(|index| index + 1 == self.songs.len());
+        let moved_on = self.started && (index.is_none() || state.context.is_some());
+        self.started |= index.is_some();
+        self.finishing |= last && state.progress_ms > 0;
+        let stopped = last && self.finishing && !state.is_playing && state.progress_ms == 0;
+        let ended = stopped || moved_on;
+        let queue = index.map_or(0, |index| self.songs.len() - index - 1);
+        let artists: Vec<String> = item.artists.into_iter().map(|artist| artist.name).collect();
+
+        Ok(Playback {
+            track: Some(Track {
+                title: item.name,
+                artist: Some(artists.join(", ")).filter(|artist| !artist.is_empty()),
+                art: item.album.images.into_iter().next().map(|image| image.url),
+                duration: Duration::from_millis(item.duration_ms),
+            }),
+            song: Some(item.uri),
+            volume: volume(&state.device),
+            position: Duration::from_millis(state.progress_ms),
+            paused: !state.is_playing,
+            queue,
+            ended,
+        })
+    }
+
+    /// The playback state of the active device, when it is the device of this player.
+    /// Without a preferred device, the player plays on whichever device is active.
+    async fn state(&mut self) -> anyhow::Result<Option<PlaybackState>> {
+        let state = self.client.get_playback_state().await?;
+        let device_id = self.device_id.as_ref();
+
+        Ok(state.filter(|state| device_id.is_none_or(|id| *id == state.device.id)))
+    }
+
     async fn resolve_uri(&mut self, uri: &str) -> anyhow::Result<Playable> {
         let uri: Uri = uri.parse()?;
 
@@ -106,6 +256,12 @@ impl Player {
     }
 }
 
+fn volume(device: &Device) -> Option<u8> {
+    device
+        .supports_volume
+        .then(|| device.volume_percent.min(100) as u8)
+}
+
 fn not_supported(status: Option<StatusCode>) -> bool {
     status == Some(StatusCode::NOT_FOUND) || status == Some(StatusCode::FORBIDDEN)
 }
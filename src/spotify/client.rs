@@ -1,4 +1,8 @@
-use crate::spotify::models::{Album, DeviceList, Playlist, StartPlaybackRequest, Track};
+use crate::spotify::models::{
+    Album, DeviceList, PlaybackState, Playlist, StartPlaybackRequest, Track,
+};
+use reqwest::StatusCode;
+use std::time::Duration;
 use crate::token;
 
 #[derive(Clone)]
@@ -32,6 +36,46 @@ impl Client {
             .await
     }
 
+    /// The playback state, or none when there is no active device.
+    pub async fn get_playback_state(&mut self) -> reqwest::Result<Option<PlaybackState>> {
+        let token = self.oauth.authorization().await.unwrap_or_default();
+
+        let response = self
+            .http
+            .get("https://api.spotify.com/v1/me/player")
+            .query(&[("market", self.market.as_str())])
+            .header("Authorization", token)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        if response.status() == StatusCode::NO_CONTENT {
+            return Ok(None);
+        }
+
+        response.json().await.map(Some)
+    }
+
+    pub async fn set_volume(
+        &mut self,
+        device_id: Option<String>,
+        volume_percent: u8,
+    ) -> reqwest::Result<()> {
+        let token = self.oauth.authorization().await.unwrap_or_default();
+
+        self.http
+            .put("https://api.spotify.com/v1/me/player/volume")
+            .query(&[("volume_percent", volume_percent.to_string())])
+            .query(&device_id.map(|id| [("device_id", id)]))
+            .header("Authorization", token)
+            .header("Content-Length", 0)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
     pub async fn play(
         &mut self,
         device_id: Option<String>,
@@ -51,6 +95,22 @@ impl Client {
         Ok(())
     }
 
+    /// Resumes the current playback, as opposed to starting new playback.
+    pub async fn resume(&mut self, device_id: Option<String>) -> reqwest::Result<()> {
+        let token = self.oauth.authorization().await.unwrap_or_default();
+
+        self.http
+            .put("https://api.spotify.com/v1/me/player/play")
+            .query(&device_id.map(|id| [("device_id", id)]))
+            .header("Authorization", token)
+            .header("Content-Length", 0)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
     pub async fn pause(&mut self, device_id: Option<String>) -> reqwest::Result<()> {
         let token = self.oauth.authorization().await.unwrap_or_default();
 
@@ -81,6 +141,42 @@ impl Client {
         Ok(())
     }
 
+    pub async fn skip_to_previous(&mut self, device_id: Option<String>) -> reqwest::Result<()> {
+        let token = self.oauth.authorization().await.unwrap_or_default();
+
+        self.http
+            .post("https://api.spotify.com/v1/me/player/previous")
+            .query(&device_id.map(|id| [("device_id", id)]))
+            .header("Authorization", token)
+            .header("Content-Length", 0)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    /// Moves to the position in the song playing.
+    pub async fn seek(
+        &mut self,
+        device_id: Option<String>,
+        position: Duration,
+    ) -> reqwest::Result<()> {
+        let token = self.oauth.authorization().await.unwrap_or_default();
+
+        self.http
+            .put("https://api.spotify.com/v1/me/player/seek")
+            .query(&[("position_ms", position.as_millis().to_string())])
+            .query(&device_id.map(|id| [("device_id", id)]))
+            .header("Authorization", token)
+            .header("Content-Length", 0)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
     pub async fn get_track(&mut self, id: &str) -> reqwest::Result<Track> {
         let token = self.oauth.authorization().await.unwrap_or_default();
 
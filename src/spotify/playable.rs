@@ -11,6 +11,7 @@ pub enum Playable {
 pub struct Song {
     pub uri: String,
     pub duration: Duration,
+    pub album: String,
 }
 
 impl Playable {
@@ -22,6 +23,7 @@ impl Playable {
                 songs.push(Song {
                     uri: track.uri.clone(),
                     duration: Duration::from_millis(track.duration_ms),
+                    album: track.album.uri.clone(),
                 });
             }
             Playable::Playlist(playlist) => {
@@ -30,6 +32,7 @@ impl Playable {
                     songs.push(Song {
                         uri: item.track.uri.clone(),
                         duration: Duration::from_millis(item.track.duration_ms),
+                        album: item.track.album.uri.clone(),
                     });
                 }
             }
@@ -40,6 +43,7 @@ impl Playable {
                         songs.push(Song {
                             uri: item.uri.clone(),
                             duration: Duration::from_millis(item.duration_ms),
+                            album: album.uri.clone(),
                         });
                     }
                 }
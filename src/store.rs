@@ -0,0 +1,89 @@
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::path::PathBuf;
+
+/// A JSON file that keeps a value across restarts, or nothing when there is no file.
+/// Saving writes a temporary file next to it and renames it over the file,
+/// so the file holds either the old or the new value, never a partial one.
+#[derive(Debug, Clone, Default)]
+pub struct Store {
+    path: Option<PathBuf>,
+}
+
+impl Store {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self { path }
+    }
+
+    /// Loads the value from the file, if any.
+    /// A missing file is treated as the default value.
+    pub fn load<T: DeserializeOwned + Default>(&self) -> anyhow::Result<T> {
+        match self.path.as_ref() {
+            Some(path) if path.exists() => {
+                Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+            }
+            _ => Ok(T::default()),
+        }
+    }
+
+    pub fn save<T: Serialize>(&self, value: &T) -> anyhow::Result<()> {
+        let Some(path) = self.path.as_ref() else {
+            return Ok(());
+        };
+
+        let mut temporary = path.clone().into_os_string();
+        temporary.push(".tmp");
+
+        std::fs::write(&temporary, serde_json::to_string_pretty(value)?)?;
+        std::fs::rename(&temporary, path)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn saves_and_loads_values() {
+        let path = std::env::temp_dir().join(format!("jukebox-store-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let store = Store::new(Some(path.clone()));
+
+        assert_eq!(store.load::<Vec<String>>().unwrap(), Vec::<String>::new());
+
+        store.save(&vec!["spotify:album:1"]).unwrap();
+        store
+            .save(&vec!["spotify:album:1", "file:///stories"])
+            .unwrap();
+
+        assert_eq!(
+            store.load::<Vec<String>>().unwrap(),
+            ["spotify:album:1", "file:///stories"]
+        );
+        assert!(!path.with_extension("json.tmp").exists());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn keeps_nothing_without_a_file() {
+        let store = Store::default();
+
+        store
+            .save(&BTreeMap::from([("04A1B2C3", "file:///stories")]))
+            .unwrap();
+
+        assert!(store.load::<BTreeMap<String, String>>().unwrap().is_empty());
+    }
+
+    #[test]
+    fn reports_failed_saves() {
+        let path = std::env::temp_dir().join("jukebox-missing-directory/store.json");
+        let store = Store::new(Some(path));
+
+        assert!(store.save(&vec!["spotify:album:1"]).is_err());
+    }
+}
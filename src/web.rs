@@ -1,32 +1,78 @@
+use crate::card::{Cards, Finalize, Program};
 use crate::console::Screen;
+use crate::player::{self, Command, Commands, Volume};
 use crate::spotify;
 use crate::token::Client;
-use axum::extract::{Form, Query, State};
-use axum::http::{header, HeaderMap, Uri};
+use axum::extract::{Form, Path, Query, State};
+use axum::http::{header, HeaderMap, StatusCode, Uri};
+use axum::response::sse::{self, KeepAlive, Sse};
 use axum::response::{Html, IntoResponse, Redirect, Response};
 use axum::routing::{get, post};
 use axum::{Json, serve};
 use oauth2::PkceCodeVerifier;
 use serde::Deserialize;
+use std::collections::BTreeMap;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::net::TcpListener;
 use tokio::sync::Mutex;
-use tokio::sync::watch::{Receiver, Sender};
+use tokio::sync::watch::Receiver;
+use tokio_stream::wrappers::{BroadcastStream, WatchStream};
+use tokio_stream::{Stream, StreamExt, StreamMap};
 
 #[derive(Deserialize)]
 struct Input {
     uri: String,
 }
 
+#[derive(Deserialize)]
+struct Position {
+    position: usize,
+}
+
+// A volume such as 40, a change such as +10 or -10, mute, unmute or toggle.
+#[derive(Deserialize)]
+struct Level {
+    volume: String,
+}
+
+// The position in the current song.
+#[derive(Deserialize)]
+struct Offset {
+    seconds: u64,
+}
+
+#[derive(Deserialize)]
+struct Binding {
+    uri: String,
+    uid: Option<String>,
+}
+
+// Checkboxes are only submitted when checked.
+#[derive(Deserialize)]
+struct Finalization {
+    lock: Option<String>,
+    protect: Option<String>,
+    dry_run: Option<String>,
+}
+
 #[derive(Deserialize)]
 struct CallbackParameters {
     code: String,
 }
 
+/// The state of the default player and of the player for the device of each zone.
+pub struct States {
+    pub default: Receiver<player::PlayerState>,
+    pub zones: BTreeMap<String, Receiver<player::PlayerState>>,
+}
+
 #[derive(Clone)]
 struct PlayerState {
-    sender: Sender<Option<String>>,
-    _receiver: Receiver<Option<String>>,
+    commands: Commands,
+    state: Receiver<player::PlayerState>,
+    zones: BTreeMap<String, Receiver<player::PlayerState>>,
+    cards: Cards,
     oauth: Client,
     screen: Screen,
     code_verifier: Arc<Mutex<Option<PkceCodeVerifier>>>,
@@ -35,15 +81,18 @@ struct PlayerState {
 
 impl PlayerState {
     fn new(
-        sender: Sender<Option<String>>,
-        _receiver: Receiver<Option<String>>,
+        commands: Commands,
+        states: States,
+        cards: Cards,
         oauth: Client,
         screen: Screen,
         client: spotify::Client,
     ) -> Self {
         Self {
-            sender,
-            _receiver,
+            commands,
+            state: states.default,
+            zones: states.zones,
+            cards,
             oauth,
             screen,
             client,
@@ -53,8 +102,9 @@ impl PlayerState {
 }
 
 pub async fn run(
-    sender: Sender<Option<String>>,
-    receiver: Receiver<Option<String>>,
+    commands: Commands,
+    states: States,
+    cards: Cards,
     oauth: Client,
     address: String,
     screen: Screen,
@@ -65,13 +115,31 @@ pub async fn run(
         .route("/", get(index))
         .route("/index.html", get(index))
         .route("/logs", get(logs))
+        .route("/logs/events", get(log_events))
+        .route("/state", get(player_state))
+        .route("/zones", get(zone_states))
+        .route("/events", get(events))
         .route("/play", post(play).put(play))
+        .route("/queue", get(queue).post(enqueue).put(enqueue))
+        .route("/queue/{index}", post(reorder).put(reorder).delete(dequeue))
+        .route("/pause", post(pause).put(pause))
+        .route("/resume", post(resume).put(resume))
+        .route("/skip", post(skip).put(skip))
+        .route("/previous", post(previous).put(previous))
+        .route("/restart", post(restart).put(restart))
+        .route("/seek", post(seek).put(seek))
+        .route("/volume", post(volume).put(volume))
+        .route("/stop", post(stop).put(stop))
+        .route("/write", post(write).put(write))
+        .route("/finalize", post(finalize).put(finalize))
+        .route("/cards", get(registry).post(bind).put(bind))
+        .route("/readers", get(readers))
         .route("/login", get(login))
         .route("/callback", get(callback))
         .route("/devices", get(devices))
         .route("/authorization", get(authorization))
         .fallback(not_found)
-        .with_state(PlayerState::new(sender, receiver, oauth, screen, client));
+        .with_state(PlayerState::new(commands, states, cards, oauth, screen, client));
 
     tracing::debug!(%address, "listening to HTTP requests");
 
@@ -80,20 +148,190 @@ pub async fn run(
     Ok(())
 }
 
-async fn logs(State(state): State<PlayerState>) -> Html<String> {
-    Html(state.screen.read())
+async fn logs() -> Html<&'static str> {
+    Html(include_str!("../public/logs.html"))
 }
 
-async fn play(State(state): State<PlayerState>, Form(input): Form<Input>) -> impl IntoResponse {
-    let value = Some(input.uri).filter(|v| !v.is_empty());
+/// Streams the latest log records and every record after them, for the client to filter.
+async fn log_events(
+    State(state): State<PlayerState>,
+) -> Sse<impl Stream<Item = Result<sse::Event, axum::Error>>> {
+    let (records, live) = state.screen.subscribe();
+    let live = BroadcastStream::new(live).filter_map(Result::ok);
+
+    let records = tokio_stream::iter(records)
+        .chain(live)
+        .map(|record| sse::Event::default().event("log").json_data(record));
+
+    Sse::new(records).keep_alive(KeepAlive::default())
+}
+
+async fn player_state(State(state): State<PlayerState>) -> Json<player::PlayerState> {
+    Json(state.state.borrow().clone())
+}
+
+/// The state of the player for the device of each zone, by device.
+async fn zone_states(
+    State(state): State<PlayerState>,
+) -> Json<BTreeMap<String, player::PlayerState>> {
+    let zones = state.zones.iter();
+    Json(zones.map(|(device, zone)| (device.clone(), zone.borrow().clone())).collect())
+}
+
+/// Streams the state of the player, of each zone and the connected readers,
+/// starting with the current ones, and the cards presented.
+async fn events(
+    State(state): State<PlayerState>,
+) -> Sse<impl Stream<Item = Result<sse::Event, axum::Error>>> {
+    let states = WatchStream::new(state.state.clone())
+        .map(|snapshot| sse::Event::default().event("state").json_data(snapshot));
+    let zones = state.zones.iter();
+    let zones = StreamMap::from_iter(
+        zones.map(|(device, zone)| (device.clone(), WatchStream::new(zone.clone()))),
+    )
+    .map(|(zone, snapshot)| {
+        let data = serde_json::json!({"zone": zone, "state": snapshot});
+        sse::Event::default().event("zone").json_data(data)
+    });
+    let readers = WatchStream::new(state.cards.watch_readers())
+        .map(|readers| sse::Event::default().event("readers").json_data(readers));
+    // Slow clients miss the cards they lagged behind on, the next state catches them up.
+    let cards = BroadcastStream::new(state.cards.subscribe())
+        .filter_map(Result::ok)
+        .map(|event| sse::Event::default().event("card").json_data(event));
+
+    Sse::new(states.merge(zones).merge(readers).merge(cards)).keep_alive(KeepAlive::default())
+}
+
+async fn play(State(state): State<PlayerState>, Form(input): Form<Input>) -> Response {
+    // An empty URI pauses the playback.
+    let command = match input.uri.is_empty() {
+        true => Command::Pause,
+        false => Command::Play(input.uri),
+    };
+
+    execute(&state, command).await
+}
+
+async fn queue(State(state): State<PlayerState>) -> Json<Vec<String>> {
+    Json(state.state.borrow().queued.clone())
+}
+
+async fn enqueue(State(state): State<PlayerState>, Form(input): Form<Input>) -> Response {
+    execute(&state, Command::Enqueue(input.uri)).await
+}
+
+async fn reorder(
+    State(state): State<PlayerState>,
+    Path(index): Path<usize>,
+    Form(Position { position }): Form<Position>,
+) -> Response {
+    execute(&state, Command::Reorder { index, position }).await
+}
 
-    if let Err(e) = state.sender.send(value) {
-        tracing::error!(%e, "Failed to set desired state as playing");
+async fn dequeue(State(state): State<PlayerState>, Path(index): Path<usize>) -> Response {
+    execute(&state, Command::Dequeue(index)).await
+}
+
+async fn volume(State(state): State<PlayerState>, Form(level): Form<Level>) -> Response {
+    match level.volume.parse::<Volume>() {
+        Ok(volume) => execute(&state, Command::Volume(volume)).await,
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
+
+async fn pause(State(state): State<PlayerState>) -> Response {
+    execute(&state, Command::Pause).await
+}
+
+async fn resume(State(state): State<PlayerState>) -> Response {
+    execute(&state, Command::Resume).await
+}
+
+async fn skip(State(state): State<PlayerState>) -> Response {
+    execute(&state, Command::Skip).await
+}
+
+async fn previous(State(state): State<PlayerState>) -> Response {
+    execute(&state, Command::Previous).await
+}
+
+async fn restart(State(state): State<PlayerState>) -> Response {
+    execute(&state, Command::Restart).await
+}
+
+async fn seek(State(state): State<PlayerState>, Form(offset): Form<Offset>) -> Response {
+    let position = Duration::from_secs(offset.seconds);
+    execute(&state, Command::Seek(position)).await
+}
+
+async fn stop(State(state): State<PlayerState>) -> Response {
+    execute(&state, Command::Stop).await
+}
+
+/// Waits for the player to carry out the command and reports whether it succeeded.
+async fn execute(state: &PlayerState, command: Command) -> Response {
+    match state.commands.execute(command).await {
+        Ok(()) => Redirect::to("/").into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
     }
+}
+
+async fn write(State(state): State<PlayerState>, Form(input): Form<Input>) -> impl IntoResponse {
+    let value = Some(input.uri).filter(|v| !v.is_empty());
+
+    // The URI is written to the next card presented to the reader.
+    state.cards.program_next(value.map(Program::Write));
+
+    Redirect::to("/")
+}
+
+async fn finalize(
+    State(state): State<PlayerState>,
+    Form(finalization): Form<Finalization>,
+) -> impl IntoResponse {
+    let finalize = Finalize {
+        lock: finalization.lock.is_some(),
+        protect: finalization.protect.is_some(),
+        dry_run: finalization.dry_run.is_some(),
+    };
+
+    // The next card presented to the reader is finalized, and the outcome is logged.
+    let program = Some(finalize)
+        .filter(|finalize| finalize.lock || finalize.protect)
+        .map(Program::Finalize);
+    state.cards.program_next(program);
 
     Redirect::to("/")
 }
 
+async fn registry(State(state): State<PlayerState>) -> Json<BTreeMap<String, String>> {
+    Json(state.cards.registry().cards())
+}
+
+async fn readers(State(state): State<PlayerState>) -> Json<Vec<String>> {
+    Json(state.cards.readers())
+}
+
+async fn bind(State(state): State<PlayerState>, Form(binding): Form<Binding>) -> impl IntoResponse {
+    match binding.uid.filter(|v| !v.is_empty()) {
+        Some(uid) => {
+            // Binding writes the registry file, so keep it off the async runtime.
+            let registry = state.cards.registry().clone();
+            let bound = tokio::task::spawn_blocking(move || registry.bind(uid, binding.uri)).await;
+
+            if let Err(e) = bound.map_err(anyhow::Error::from).and_then(|bound| bound) {
+                tracing::error!(%e, "Failed to bind the card");
+                return (StatusCode::BAD_REQUEST, e.to_string()).into_response();
+            }
+        }
+        // The next card presented to the reader is bound to the URI.
+        None => state.cards.program_next(Some(Program::Bind(binding.uri))),
+    }
+
+    Redirect::to("/").into_response()
+}
+
 async fn devices(State(mut state): State<PlayerState>) -> Response {
     match state.client.get_available_devices().await {
         Ok(devices) => Json(devices).into_response(),
@@ -0,0 +1,133 @@
+use crate::card::feedback::Feedback;
+use crate::player::Commands;
+use anyhow::anyhow;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Assigns the readers whose name contains a pattern to the player for a Spotify device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Zone {
+    pub reader: String,
+    pub device: String,
+}
+
+impl FromStr for Zone {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((reader, device)) if !reader.is_empty() && !device.is_empty() => Ok(Zone {
+                reader: reader.to_string(),
+                device: device.to_string(),
+            }),
+            _ => Err(anyhow!("Invalid zone {s:?}, expected <reader>=<device>")),
+        }
+    }
+}
+
+impl Zone {
+    /// The file the player of the zone keeps its queue in, next to the one of the default player.
+    /// Characters of the device name that are not letters or digits are replaced,
+    /// so the name cannot leave the directory.
+    pub fn queue_file(&self, path: &Path) -> PathBuf {
+        let device: String = self
+            .device
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '-' })
+            .collect();
+
+        path.with_extension(format!("{device}.json"))
+    }
+}
+
+/// The player of a zone and the feedback to its readers.
+#[derive(Clone)]
+pub struct Route {
+    pub sender: Commands,
+    pub feedback: Feedback,
+}
+
+/// Routes the cards presented to each reader to the player of its zone.
+/// Readers without a zone share the default player.
+pub struct Zones {
+    default: Route,
+    zones: Vec<(String, Route)>,
+}
+
+impl Zones {
+    pub fn new(default: Route) -> Self {
+        Self {
+            default,
+            zones: Vec::new(),
+        }
+    }
+
+    /// Routes the readers whose name contains the pattern.
+    /// Patterns are matched in the order they are inserted.
+    pub fn insert(&mut self, reader: String, route: Route) {
+        self.zones.push((reader, route));
+    }
+
+    pub fn route(&self, reader: &str) -> &Route {
+        self.zones
+            .iter()
+            .find(|(pattern, _)| reader.contains(pattern.as_str()))
+            .map(|(_, route)| route)
+            .unwrap_or(&self.default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_zone() {
+        let zone: Zone = "ACR1252 1S CL Reader PICC 0=Kitchen".parse().unwrap();
+
+        assert_eq!(zone.reader, "ACR1252 1S CL Reader PICC 0");
+        assert_eq!(zone.device, "Kitchen");
+    }
+
+    #[test]
+    fn rejects_invalid_zones() {
+        assert!("Kitchen".parse::<Zone>().is_err());
+        assert!("=Kitchen".parse::<Zone>().is_err());
+        assert!("PICC 0=".parse::<Zone>().is_err());
+    }
+
+    #[test]
+    fn keeps_zone_queue_next_to_default_queue() {
+        let zone: Zone = "PICC 1=../Living Room".parse().unwrap();
+
+        assert_eq!(
+            zone.queue_file(Path::new("/var/lib/jukebox/queue.json")),
+            Path::new("/var/lib/jukebox/queue.---Living-Room.json")
+        );
+    }
+
+    fn route() -> Route {
+        let (sender, _) = crate::player::channel();
+        let (signals, _) = std::sync::mpsc::channel();
+
+        Route {
+            sender,
+            feedback: Feedback::new(signals),
+        }
+    }
+
+    #[test]
+    fn routes_readers_to_zones() {
+        let default = route();
+        let kitchen = route();
+        let mut zones = Zones::new(default.clone());
+
+        zones.insert("PICC 1".to_string(), kitchen.clone());
+
+        let routed = zones.route("ACS ACR1252 PICC 1");
+        assert!(routed.sender.same_channel(&kitchen.sender));
+
+        let routed = zones.route("ACS ACR1252 PICC 0");
+        assert!(routed.sender.same_channel(&default.sender));
+    }
+}